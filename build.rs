@@ -0,0 +1,39 @@
+use std::path::{Path, PathBuf};
+
+/// With the `embedded_assets` feature, lists every file in `assets/` for `include_bytes!` in
+/// `src/assets/embedded_asset_io.rs`
+fn main() {
+    println!("cargo:rerun-if-changed=build.rs");
+    if std::env::var_os("CARGO_FEATURE_EMBEDDED_ASSETS").is_none() {
+        return;
+    }
+
+    let assets_dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("assets");
+    let mut files = Vec::new();
+    collect_files(&assets_dir, &mut files).expect("failed to list assets");
+    files.sort();
+
+    let mut source = String::from("&[\n");
+    for file in &files {
+        println!("cargo:rerun-if-changed={}", file.display());
+        let path = file.strip_prefix(&assets_dir).unwrap().to_string_lossy().replace('\\', "/");
+        source += &format!("\t({:?}, include_bytes!({:?})),\n", path, file);
+    }
+    source += "]\n";
+    let out_path = Path::new(&std::env::var("OUT_DIR").unwrap()).join("embedded_assets.rs");
+    std::fs::write(out_path, source).expect("failed to write the embedded assets list");
+}
+
+fn collect_files(dir: &Path, files: &mut Vec<PathBuf>) -> std::io::Result<()> {
+    // picks up added and removed files
+    println!("cargo:rerun-if-changed={}", dir.display());
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            collect_files(&path, files)?;
+        } else {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
@@ -1,11 +1,13 @@
 use crate::BevyState;
-use bevy::asset::{HandleId, LoadState};
+use bevy::asset::{AssetLoader, BoxedFuture, HandleId, LoadContext, LoadState};
 use bevy::ecs::schedule::ParallelSystemDescriptor;
 use bevy::prelude::*;
-use bevy::utils::HashSet;
+use bevy::utils::{HashMap, HashSet};
 use std::fmt::Debug;
 use std::hash::Hash;
+use std::path::{Path, PathBuf};
 use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::{Arc, Mutex};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, SystemLabel)]
 pub enum LoadingLabel {
@@ -18,19 +20,22 @@ pub struct LoadingPlugin<S: BevyState> {
     pub loading_state: S,
     /// The next state to transition to, when all progress completes
     pub next_state: S,
+    /// The state to transition to instead, when some of the assets failed to load
+    pub error_state: S,
 }
 
 impl<S: BevyState> Plugin for LoadingPlugin<S> {
     fn build(&self, app: &mut App) {
         app.init_resource::<AssetsLoading>();
+        app.init_resource::<LoadErrors>();
         app.add_system_set(SystemSet::on_enter(self.loading_state.clone()).with_system(loadstate_enter));
         app.add_system_set(
             SystemSet::on_update(self.loading_state.clone())
                 .with_system(clear_progress.label(LoadingLabel::Pre))
                 .with_system(
                     check_progress::<S>
-                        .config(|(s, _, _)| {
-                            *s = Some(Some(self.next_state.clone()));
+                        .config(|(s, _, _, _)| {
+                            *s = Some(Some((self.next_state.clone(), self.error_state.clone())));
                         })
                         .label(LoadingLabel::Post),
                 )
@@ -44,8 +49,9 @@ impl<S: BevyState> Plugin for LoadingPlugin<S> {
     }
 }
 
-fn loadstate_enter(mut commands: Commands) {
+fn loadstate_enter(mut commands: Commands, mut loading: ResMut<AssetsLoading>) {
     commands.insert_resource(ProgressCounter::default());
+    loading.failed.clear();
 }
 
 fn loadstate_exit(mut commands: Commands) {
@@ -53,20 +59,29 @@ fn loadstate_exit(mut commands: Commands) {
 }
 
 fn assets_loading_reset(mut loading: ResMut<AssetsLoading>) {
-    *loading = AssetsLoading::default();
+    // failures are kept around for the error state to list
+    let failed = std::mem::take(&mut loading.failed);
+    *loading = AssetsLoading {
+        failed,
+        ..Default::default()
+    };
 }
 
-fn assets_progress(mut loading: ResMut<AssetsLoading>, server: Res<AssetServer>) -> Progress {
+fn assets_progress(mut loading: ResMut<AssetsLoading>, server: Res<AssetServer>, errors: Res<LoadErrors>) -> Progress {
     // TODO: avoid this temporary vec (HashSet::drain_filter is in Rust nightly)
     let mut done = vec![];
     for handle in loading.handles.iter() {
         let load_state = server.get_load_state(*handle);
         if load_state != LoadState::Loading && load_state != LoadState::NotLoaded {
-            done.push(*handle);
+            done.push((*handle, load_state));
         }
     }
-    for handle in done {
+    for (handle, load_state) in done {
         loading.handles.remove(&handle);
+
+        if load_state == LoadState::Failed {
+            loading.fail_load(handle, &server, &errors);
+        }
     }
 
     Progress {
@@ -83,7 +98,12 @@ fn tracker(In(progress): In<Progress>, counter: Res<ProgressCounter>) {
     counter.manually_tick(progress);
 }
 
-fn check_progress<S: BevyState>(next_state: Local<Option<S>>, mut counter: ResMut<ProgressCounter>, mut state: ResMut<State<S>>) {
+fn check_progress<S: BevyState>(
+    next_states: Local<Option<(S, S)>>,
+    loading: Res<AssetsLoading>,
+    mut counter: ResMut<ProgressCounter>,
+    mut state: ResMut<State<S>>,
+) {
     let total = counter.total.load(Ordering::Acquire);
     let done = counter.done.load(Ordering::Acquire);
 
@@ -93,8 +113,12 @@ fn check_progress<S: BevyState>(next_state: Local<Option<S>>, mut counter: ResMu
     counter.last_progress = progress;
 
     if progress.is_ready() {
-        if let Some(next_state) = &*next_state {
-            state.set(next_state.clone()).ok();
+        if let Some((next_state, error_state)) = &*next_states {
+            if loading.failed.is_empty() {
+                state.set(next_state.clone()).ok();
+            } else {
+                state.set(error_state.clone()).ok();
+            }
         }
     }
 }
@@ -108,6 +132,7 @@ fn clear_progress(counter: ResMut<ProgressCounter>) {
 pub struct AssetsLoading {
     handles: HashSet<HandleId>,
     total: u32,
+    failed: Vec<FailedAsset>,
 }
 
 impl AssetsLoading {
@@ -116,6 +141,86 @@ impl AssetsLoading {
         self.handles.insert(handle.into());
         self.total += 1;
     }
+
+    /// Tracked assets that failed to load, these stay around until the next time loading starts
+    pub fn failed(&self) -> &[FailedAsset] {
+        &self.failed
+    }
+
+    /// Fails loading because of an asset that loaded fine, but turned out to be unusable
+    pub fn fail<T: Into<HandleId>>(&mut self, handle: T, path: Option<PathBuf>, error: String) {
+        // progress systems see the same failure every frame
+        let handle = handle.into();
+        if self.failed.iter().all(|failed| failed.handle != handle) {
+            self.failed.push(FailedAsset { handle, path, error });
+        }
+    }
+
+    /// Fails loading because of an asset the asset server couldn't load, with the error its loader returned
+    pub fn fail_load<T: Into<HandleId>>(&mut self, handle: T, server: &AssetServer, errors: &LoadErrors) {
+        let handle = handle.into();
+        let path = server.get_handle_path(handle).map(|path| path.path().to_owned());
+        let error = path
+            .as_ref()
+            .and_then(|path| errors.get(path))
+            .unwrap_or_else(|| "couldn't be read, or there's no loader for it".to_owned());
+        self.fail(handle, path, error);
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct FailedAsset {
+    pub handle: HandleId,
+    pub path: Option<PathBuf>,
+    pub error: String,
+}
+
+/// Errors returned by the asset loaders by asset path, the asset server only logs them
+#[derive(Clone, Default)]
+pub struct LoadErrors(Arc<Mutex<HashMap<PathBuf, String>>>);
+
+impl LoadErrors {
+    pub fn get(&self, path: &Path) -> Option<String> {
+        self.0.lock().unwrap().get(path).cloned()
+    }
+}
+
+/// Wraps a loader to keep the errors it returns in `LoadErrors`
+struct ErrorRecordingLoader<L> {
+    loader: L,
+    errors: LoadErrors,
+}
+
+impl<L: AssetLoader> AssetLoader for ErrorRecordingLoader<L> {
+    fn load<'a>(&'a self, bytes: &'a [u8], load_context: &'a mut LoadContext) -> BoxedFuture<'a, Result<(), anyhow::Error>> {
+        let path = load_context.path().to_owned();
+        Box::pin(async move {
+            let result = self.loader.load(bytes, load_context).await;
+            let mut errors = self.errors.0.lock().unwrap();
+            match &result {
+                Ok(()) => errors.remove(&path),
+                Err(error) => errors.insert(path, format!("{:#}", error)),
+            };
+            result
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        self.loader.extensions()
+    }
+}
+
+pub trait RecordLoadErrors {
+    /// Same as `init_asset_loader`, but the errors of the loader end up in `LoadErrors`
+    fn init_recording_asset_loader<L: AssetLoader + FromWorld>(&mut self) -> &mut Self;
+}
+
+impl RecordLoadErrors for App {
+    fn init_recording_asset_loader<L: AssetLoader + FromWorld>(&mut self) -> &mut Self {
+        let loader = L::from_world(&mut self.world);
+        let errors = self.world.get_resource_or_insert_with(LoadErrors::default).clone();
+        self.add_asset_loader(ErrorRecordingLoader { loader, errors })
+    }
 }
 
 /// Resource for tracking overall progress
@@ -4,16 +4,24 @@ use bevy::{
     reflect::TypeUuid,
     utils::BoxedFuture,
 };
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 
-use crate::{MAP_SIZE_HEIGHT, MAP_SIZE_WIDTH};
+use super::level_pack::{LevelPackAsset, LevelPackHeader};
+use super::level_validation::LevelValidation;
+use super::AssetLoading::RecordLoadErrors;
 
-#[derive(Debug, TypeUuid)]
+/// Newest level file format this build understands.
+///
+/// Version 1 files only contain `rows`, version 2 adds the optional `header`.
+pub const LEVEL_FORMAT_VERSION: u32 = 2;
+
+#[derive(Debug, PartialEq, TypeUuid)]
 #[uuid = "4b05438e-39d9-4b57-9a3a-061ed489b5c9"]
 pub struct LevelDataAsset {
     pub tiles: Vec<LevelTile>,
     pub width: i32,
     pub height: i32,
+    pub header: LevelHeader,
 }
 
 impl LevelDataAsset {
@@ -22,6 +30,73 @@ impl LevelDataAsset {
             tiles: Vec::new(),
             width: 0,
             height: 0,
+            header: LevelHeader::default(),
+        }
+    }
+
+    /// Builds a level from rows of glyphs, listed top to bottom.
+    ///
+    /// Unknown glyphs and anything outside of `width`x`height` are skipped, see `LevelValidation::check_rows`
+    pub fn from_rows(rows: &[String], width: i32, height: i32, glyphs: fn(char) -> Option<TileType>) -> Self {
+        let mut level_data = LevelDataAsset::new();
+        level_data.width = width;
+        level_data.height = height;
+
+        // rows are listed top to bottom, but y grows upwards
+        let mut y = height - 1;
+        for row_data in rows {
+            for (x, ch) in row_data.chars().enumerate() {
+                let p = IVec2::new(x as i32, y);
+                if p.x >= width || p.y < 0 {
+                    continue;
+                }
+
+                if let Some(tile_type) = glyphs(ch) {
+                    level_data.tiles.push(LevelTile::new(tile_type, p));
+                }
+            }
+            y -= 1;
+        }
+
+        level_data
+    }
+
+    /// The same level flipped left to right
+    pub fn mirrored(&self) -> Self {
+        Self {
+            tiles: self
+                .tiles
+                .iter()
+                .map(|tile| LevelTile::new(tile.behaviour, IVec2::new(self.width - 1 - tile.position.x, tile.position.y)))
+                .collect(),
+            width: self.width,
+            height: self.height,
+            header: self.header.clone(),
+        }
+    }
+
+    /// Converts the level back into its on disk representation, the inverse of `parse_level`
+    pub fn to_disk(&self) -> LevelDataDiskAsset {
+        let mut grid = vec![vec![' '; self.width.max(0) as usize]; self.height.max(0) as usize];
+        for tile in &self.tiles {
+            // same as `from_rows`, anything outside of the level is dropped
+            let p = tile.position;
+            if p.x < 0 || p.x >= self.width || p.y < 0 || p.y >= self.height {
+                continue;
+            }
+
+            let row = (self.height - 1 - tile.position.y) as usize;
+            grid[row][tile.position.x as usize] = tile.behaviour.glyph();
+        }
+
+        let has_header = self.header != LevelHeader::default();
+        LevelDataDiskAsset {
+            version: if has_header { LEVEL_FORMAT_VERSION } else { 1 },
+            header: if has_header { Some(self.header.clone()) } else { None },
+            // every row gets written out in full, so the size can always be inferred
+            width: None,
+            height: None,
+            rows: grid.into_iter().map(|row| row.into_iter().collect()).collect(),
         }
     }
 }
@@ -32,12 +107,73 @@ impl Default for LevelDataAsset {
     }
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Serialize)]
 pub struct LevelDataDiskAsset {
+    #[serde(
+        default = "LevelDataDiskAsset::default_version",
+        skip_serializing_if = "LevelDataDiskAsset::is_default_version"
+    )]
+    pub version: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub header: Option<LevelHeader>,
+    /// Explicit level size, inferred from `rows` when omitted
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub width: Option<i32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub height: Option<i32>,
     pub rows: Vec<String>,
 }
 
-#[derive(Debug)]
+impl LevelDataDiskAsset {
+    // files without a version predate the header, so treat them as version 1
+    fn default_version() -> u32 {
+        1
+    }
+
+    fn is_default_version(version: &u32) -> bool {
+        *version == Self::default_version()
+    }
+
+    pub fn inferred_width(&self) -> i32 {
+        self.rows.iter().map(|row| row.chars().count()).max().unwrap_or(0) as i32
+    }
+
+    pub fn inferred_height(&self) -> i32 {
+        self.rows.len() as i32
+    }
+}
+
+/// Descriptive information about a level, for menus, the hud and tools
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct LevelHeader {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub author: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub par_seconds: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub difficulty: Option<LevelDifficulty>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub notes: Option<String>,
+    /// Tileset to draw the level with, ie. `"ice"` for `tilesets/ice.tileset`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub theme: Option<String>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LevelDifficulty {
+    Easy,
+    Normal,
+    Hard,
+    Expert,
+}
+
+#[derive(Debug, PartialEq)]
 pub struct LevelTile {
     pub position: IVec2,
     pub behaviour: TileType,
@@ -52,7 +188,8 @@ impl LevelTile {
     }
 }
 
-#[derive(Clone, Copy, Debug, PartialEq)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub enum TileType {
     Brick,
     SolidBrick,
@@ -65,6 +202,119 @@ pub enum TileType {
     Player,
 }
 
+impl TileType {
+    pub const ALL: [TileType; 9] = [
+        TileType::Brick,
+        TileType::SolidBrick,
+        TileType::Ladder,
+        TileType::Rope,
+        TileType::FalseBrick,
+        TileType::HiddenLadder,
+        TileType::Gold,
+        TileType::Guard,
+        TileType::Player,
+    ];
+
+    pub fn from_glyph(glyph: char) -> Option<TileType> {
+        match glyph {
+            '#' => Some(TileType::Brick),
+            '@' => Some(TileType::SolidBrick),
+            'H' => Some(TileType::Ladder),
+            '-' => Some(TileType::Rope),
+            'X' => Some(TileType::FalseBrick),
+            'S' => Some(TileType::HiddenLadder),
+            '$' => Some(TileType::Gold),
+            '0' => Some(TileType::Guard),
+            '&' => Some(TileType::Player),
+            _ => None,
+        }
+    }
+
+    pub fn glyph(self) -> char {
+        match self {
+            TileType::Brick => '#',
+            TileType::SolidBrick => '@',
+            TileType::Ladder => 'H',
+            TileType::Rope => '-',
+            TileType::FalseBrick => 'X',
+            TileType::HiddenLadder => 'S',
+            TileType::Gold => '$',
+            TileType::Guard => '0',
+            TileType::Player => '&',
+        }
+    }
+}
+
+/// Parses the contents of a `.level` file, along with anything validation had to say about it
+pub fn parse_level(bytes: &[u8]) -> Result<(LevelDataAsset, LevelValidation), anyhow::Error> {
+    decode_level(serde_json::de::from_slice::<LevelDataDiskAsset>(bytes)?)
+}
+
+/// Converts a level from its on disk representation, the inverse of `LevelDataAsset::to_disk`
+pub fn decode_level(loaded_data: LevelDataDiskAsset) -> Result<(LevelDataAsset, LevelValidation), anyhow::Error> {
+    if loaded_data.version > LEVEL_FORMAT_VERSION {
+        anyhow::bail!(
+            "level format version {} is newer than the supported version {}",
+            loaded_data.version,
+            LEVEL_FORMAT_VERSION
+        );
+    }
+
+    let width = loaded_data.width.unwrap_or_else(|| loaded_data.inferred_width());
+    let height = loaded_data.height.unwrap_or_else(|| loaded_data.inferred_height());
+
+    let mut validation = LevelValidation::default();
+    validation.check_rows(&loaded_data.rows, width, height, TileType::from_glyph);
+
+    let mut level_data = LevelDataAsset::from_rows(&loaded_data.rows, width, height, TileType::from_glyph);
+    level_data.header = loaded_data.header.unwrap_or_default();
+    validation.check_level(&level_data);
+
+    Ok((level_data, validation))
+}
+
+/// Writes a level out in the same format as the files in `assets/levels`
+pub fn write_level(level: &LevelDataAsset) -> Result<String, anyhow::Error> {
+    let mut bytes = Vec::new();
+    let formatter = serde_json::ser::PrettyFormatter::with_indent(b"\t");
+    let mut serializer = serde_json::Serializer::with_formatter(&mut bytes, formatter);
+    level.to_disk().serialize(&mut serializer)?;
+    Ok(String::from_utf8(bytes)?)
+}
+
+/// Label of the `index`th (zero based) level in a file holding several levels, numbered from one
+pub fn level_label(index: usize) -> String {
+    format!("{:03}", index + 1)
+}
+
+/// Adds each level as a labelled asset numbered from one, ie. `pack.lrpack#017`, along with a
+/// `LevelPackAsset` listing them as the default asset.
+///
+/// Fails on the first level with errors, warnings are only printed
+pub fn set_level_pack(
+    load_context: &mut LoadContext,
+    header: LevelPackHeader,
+    levels: Vec<(LevelDataAsset, LevelValidation)>,
+) -> Result<(), anyhow::Error> {
+    let mut handles = Vec::new();
+    for (index, (level_data, validation)) in levels.into_iter().enumerate() {
+        let label = level_label(index);
+        let path = format!("{}#{}", load_context.path().display(), label);
+        for warning in validation.warnings() {
+            println!("WARNING:  {}", warning.report(&path));
+        }
+        if validation.has_errors() {
+            let errors: Vec<String> = validation.errors().map(|e| e.report(&path)).collect();
+            anyhow::bail!("invalid level:\n{}", errors.join("\n"));
+        }
+
+        handles.push(load_context.set_labeled_asset(&label, LoadedAsset::new(level_data)));
+    }
+
+    load_context.set_default_asset(LoadedAsset::new(LevelPackAsset { header, levels: handles }));
+    Ok(())
+}
+
 #[derive(Default)]
 pub struct LevelDataAssetLoader;
 
@@ -73,33 +323,13 @@ impl AssetLoader for LevelDataAssetLoader {
         Box::pin(async move {
             // random sleep for testing the loading screen
             //std::thread::sleep(std::time::Duration::from_millis((1+(rand::random::<u64>() % 24)) * 1000));
-            let loaded_data = serde_json::de::from_slice::<LevelDataDiskAsset>(bytes)?;
-            let mut level_data = LevelDataAsset::new();
-            level_data.width = MAP_SIZE_WIDTH;
-            level_data.height = MAP_SIZE_HEIGHT;
-
-            let mut y = loaded_data.rows.len() as i32 - 1;
-            for row_data in &loaded_data.rows {
-                for (x, ch) in row_data.chars().enumerate() {
-                    let p = IVec2::new(x as i32, y);
-
-                    match ch {
-                        '#' => level_data.tiles.push(LevelTile::new(TileType::Brick, p)),
-                        '@' => level_data.tiles.push(LevelTile::new(TileType::SolidBrick, p)),
-                        'H' => level_data.tiles.push(LevelTile::new(TileType::Ladder, p)),
-                        '-' => level_data.tiles.push(LevelTile::new(TileType::Rope, p)),
-                        'X' => level_data.tiles.push(LevelTile::new(TileType::FalseBrick, p)),
-                        'S' => level_data.tiles.push(LevelTile::new(TileType::HiddenLadder, p)),
-                        '$' => level_data.tiles.push(LevelTile::new(TileType::Gold, p)),
-                        '0' => level_data.tiles.push(LevelTile::new(TileType::Guard, p)),
-                        '&' => level_data.tiles.push(LevelTile::new(TileType::Player, p)),
-                        ' ' => {}
-                        _ => {
-                            println!("WARNING:  unexpected tile type: {} found!", ch);
-                        }
-                    }
-                }
-                y -= 1;
+            let (level_data, validation) = parse_level(bytes)?;
+            for warning in validation.warnings() {
+                println!("WARNING:  {}", warning.report(load_context.path().display()));
+            }
+            if validation.has_errors() {
+                let errors: Vec<String> = validation.errors().map(|e| e.report(load_context.path().display())).collect();
+                anyhow::bail!("invalid level:\n{}", errors.join("\n"));
             }
 
             load_context.set_default_asset(LoadedAsset::new(level_data));
@@ -117,6 +347,83 @@ pub struct LevelDataAssetPlugin;
 impl Plugin for LevelDataAssetPlugin {
     fn build(&self, app: &mut App) {
         app.add_asset::<LevelDataAsset>();
-        app.init_asset_loader::<LevelDataAssetLoader>();
+        app.add_asset::<LevelPackAsset>();
+        app.init_recording_asset_loader::<LevelDataAssetLoader>();
+        app.init_recording_asset_loader::<super::level_pack::LevelPackAssetLoader>();
+        app.init_recording_asset_loader::<super::apple2_levels::Apple2LevelsAssetLoader>();
+        app.init_recording_asset_loader::<super::lrtr_levels::LrtrLevelsAssetLoader>();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::assets::test_util::{asset_files, read_asset_bytes};
+
+    #[test]
+    fn classic_levels_round_trip() {
+        let levels = asset_files("levels/classic", "level");
+        assert!(!levels.is_empty());
+
+        for path in levels {
+            let original = String::from_utf8(read_asset_bytes(&path)).unwrap();
+            let (level, _) = parse_level(original.as_bytes()).unwrap();
+
+            let written = write_level(&level).unwrap();
+            assert_eq!(original, written, "{} didn't round trip", path.display());
+
+            let (reloaded, _) = parse_level(written.as_bytes()).unwrap();
+            assert_eq!(level, reloaded, "{} didn't round trip", path.display());
+        }
+    }
+
+    #[test]
+    fn header_round_trips() {
+        let (mut level, _) = parse_level(&read_asset_bytes("levels/classic/001.level")).unwrap();
+        level.header = LevelHeader {
+            name: Some("Round Trip".to_owned()),
+            author: Some("Someone".to_owned()),
+            par_seconds: Some(90),
+            difficulty: Some(LevelDifficulty::Hard),
+            tags: vec!["classic".to_owned(), "test".to_owned()],
+            notes: Some("keeps \"quotes\" too".to_owned()),
+            theme: Some("ice".to_owned()),
+        };
+
+        let written = write_level(&level).unwrap();
+        let (reloaded, _) = parse_level(written.as_bytes()).unwrap();
+        assert_eq!(level, reloaded);
+        assert!(written.contains("\"version\": 2"));
+    }
+
+    #[test]
+    fn mirroring_flips_rows() {
+        let (level, _) = parse_level(&read_asset_bytes("levels/classic/001.level")).unwrap();
+        let mirrored = level.mirrored();
+
+        let rows = level.to_disk().rows;
+        let mirrored_rows = mirrored.to_disk().rows;
+        for (row, mirrored_row) in rows.iter().zip(&mirrored_rows) {
+            assert_eq!(row.chars().rev().collect::<String>(), *mirrored_row);
+        }
+        assert_eq!(mirrored.mirrored(), level);
+    }
+
+    #[test]
+    fn empty_size_is_an_error() {
+        use crate::assets::level_validation::LevelDiagnosticKind;
+
+        let (_, validation) = parse_level(br##"{ "width": 0, "height": 2, "rows": ["&", "#"] }"##).unwrap();
+        let errors: Vec<_> = validation.errors().map(|e| &e.kind).collect();
+        assert!(errors.contains(&&LevelDiagnosticKind::BadDimensions { width: 0, height: 2 }));
+        assert!(!errors.contains(&&LevelDiagnosticKind::MissingPlayer));
+    }
+
+    #[test]
+    fn out_of_bounds_tiles_are_dropped() {
+        let mut level = LevelDataAsset::from_rows(&["&$".to_owned()], 2, 1, TileType::from_glyph);
+        level.tiles.push(LevelTile::new(TileType::Gold, IVec2::new(2, 0)));
+        level.tiles.push(LevelTile::new(TileType::Gold, IVec2::new(0, -1)));
+        assert_eq!(level.to_disk().rows, ["&$"]);
     }
 }
@@ -1,16 +1,132 @@
 use bevy::{
-    asset::{AssetLoader, BoxedFuture, LoadContext, LoadedAsset},
+    asset::{AssetLoader, AssetPath, BoxedFuture, LoadContext, LoadedAsset},
     prelude::*,
     reflect::TypeUuid,
 };
 use serde::Deserialize;
 use std::collections::HashMap;
 
+use super::animgraph_asset::AnimGraphAsset;
+use super::atlas_asset::AtlasDiskAsset;
+use super::AssetLoading::RecordLoadErrors;
+use super::AssetLoading::{AssetsLoading, LoadErrors, Progress};
+
+/// Removes the entity, ie. effects that only play once
+pub const DESPAWN_EVENT: &str = "despawn";
+/// The runner's dig is over and they can move again
+pub const DIG_FINISHED_EVENT: &str = "digFinished";
+/// Every event something listens for, anims using any other name fail to load
+pub const ANIMATION_EVENTS: [&str; 2] = [DESPAWN_EVENT, DIG_FINISHED_EVENT];
+
 #[derive(Debug, TypeUuid, Deserialize)]
 #[uuid = "cf714ee8-e1ae-4ace-8467-a1ba1cf357ab"]
 pub struct AnimAsset {
     pub fps: f32,
     pub sequence: HashMap<String, AnimSequence>,
+    /// Path of the `.atlas` the frames index into, checked when loading
+    #[serde(default)]
+    pub atlas: Option<String>,
+    /// Path of the `.animgraph` driving the anim, which needs all of its sequences to be there
+    #[serde(default)]
+    pub animgraph: Option<String>,
+    #[serde(skip)]
+    pub graph: Option<Handle<AnimGraphAsset>>,
+}
+
+impl AnimAsset {
+    /// Fills in the sequences that mirror another one, returning whatever couldn't be resolved
+    pub fn resolve_mirrors(&mut self) -> Vec<String> {
+        let mut problems = Vec::new();
+
+        let mut mirrors: Vec<(String, String)> = self
+            .sequence
+            .iter()
+            .filter_map(|(name, sequence)| Some((name.clone(), sequence.mirror.clone()?)))
+            .collect();
+        mirrors.sort();
+        for (name, source_name) in mirrors {
+            if !self.sequence[&name].frames.is_empty() {
+                problems.push(format!("sequence {:?} mirrors {:?}, but has frames of its own", name, source_name));
+                continue;
+            }
+
+            let source = match self.sequence.get(&source_name) {
+                Some(source) if source.mirror.is_none() => source,
+                Some(_) => {
+                    problems.push(format!("sequence {:?} mirrors {:?}, which is a mirror itself", name, source_name));
+                    continue;
+                }
+                None => {
+                    problems.push(format!("sequence {:?} mirrors {:?}, which doesn't exist", name, source_name));
+                    continue;
+                }
+            };
+
+            let mirrored = AnimSequence {
+                mirror: Some(source_name.clone()),
+                flip_x: true,
+                ..source.clone()
+            };
+            self.sequence.insert(name, mirrored);
+        }
+
+        problems
+    }
+
+    /// Everything wrong with the anim, given how many frames its atlas has and the animgraph driving it
+    pub fn validate(&self, atlas_len: Option<usize>, graph: Option<&AnimGraphAsset>) -> Vec<String> {
+        let mut problems = Vec::new();
+
+        let mut names: Vec<&String> = self.sequence.keys().collect();
+        names.sort();
+        for name in names {
+            let sequence = &self.sequence[name];
+            let frames = &sequence.frames;
+            if frames.is_empty() {
+                problems.push(format!("sequence {:?} has no frames", name));
+            }
+            if sequence.durations.len() > frames.len() {
+                problems.push(format!(
+                    "sequence {:?} has {} durations, but only has {} frames",
+                    name,
+                    sequence.durations.len(),
+                    frames.len()
+                ));
+            }
+            if sequence.durations.iter().any(|duration| *duration <= 0.0) || matches!(sequence.fps, Some(fps) if fps <= 0.0) {
+                problems.push(format!("sequence {:?} has frames that never end", name));
+            }
+            for event in sequence.events.iter().filter(|e| e.frame >= frames.len()) {
+                problems.push(format!(
+                    "sequence {:?} has event {:?} on frame {}, but only has {} frames",
+                    name,
+                    event.name,
+                    event.frame,
+                    frames.len()
+                ));
+            }
+            let event_names = sequence.events.iter().map(|e| &e.name).chain(&sequence.on_finished);
+            for event in event_names.filter(|event| !ANIMATION_EVENTS.contains(&event.as_str())) {
+                problems.push(format!("sequence {:?} sends {:?}, which nothing listens for", name, event));
+            }
+            if let (Some(atlas_len), Some(frame)) = (atlas_len, frames.iter().max()) {
+                if *frame >= atlas_len {
+                    problems.push(format!(
+                        "sequence {:?} uses frame {}, but the atlas only has {} frames",
+                        name, frame, atlas_len
+                    ));
+                }
+            }
+        }
+
+        if let (Some(animgraph), Some(graph)) = (&self.animgraph, graph) {
+            for name in graph.sequences().into_iter().filter(|name| !self.sequence.contains_key(*name)) {
+                problems.push(format!("animgraph {:?} needs a {:?} sequence", animgraph, name));
+            }
+        }
+
+        problems
+    }
 }
 
 impl Default for AnimAsset {
@@ -18,24 +134,74 @@ impl Default for AnimAsset {
         Self {
             fps: 1.0,
             sequence: HashMap::new(),
+            atlas: None,
+            animgraph: None,
+            graph: None,
         }
     }
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
 pub struct AnimSequence {
     pub frames: Vec<usize>,
     pub looping: bool,
+    /// Name of a sequence this one plays flipped horizontally, instead of having frames of its own
+    pub mirror: Option<String>,
+    /// Set on mirrored sequences once they've been resolved
+    #[serde(skip)]
+    pub flip_x: bool,
+    /// Plays forwards then backwards, a looping sequence keeps bouncing, otherwise it finishes back on the first frame
+    pub ping_pong: bool,
+    /// Hide the sprite once a sequence that doesn't loop is over, instead of holding its last frame
+    pub hide_on_finished: bool,
+    /// Overrides the anim's fps for this sequence
+    pub fps: Option<f32>,
+    /// How many ticks at the sequence's fps each frame is shown for, 1 for any frame without a duration
+    pub durations: Vec<f32>,
+    /// Named events sent when a frame is reached
+    pub events: Vec<AnimFrameEvent>,
+    /// Event sent once a sequence that doesn't loop is over
+    pub on_finished: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct AnimFrameEvent {
+    /// Index into the sequence's `frames`, not the atlas
+    pub frame: usize,
+    pub name: String,
 }
 
 impl AnimSequence {
-    pub fn next_frame(&self, frame: usize) -> usize {
-        if self.looping {
-            (frame + 1) % self.frames.len()
-        } else if frame >= (self.frames.len() - 1) {
-            self.frames.len() - 1
+    /// Seconds a frame of the sequence stays on screen
+    pub fn frame_time(&self, frame: usize, anim_fps: f32) -> f32 {
+        let ticks = self.durations.get(frame).copied().unwrap_or(1.0);
+        ticks / self.fps.unwrap_or(anim_fps)
+    }
+
+    /// Names of the events on a frame of the sequence
+    pub fn events_on(&self, frame: usize) -> impl Iterator<Item = &str> {
+        self.events.iter().filter(move |e| e.frame == frame).map(|e| e.name.as_str())
+    }
+
+    /// The frame to show after `frame` and whether we're then playing backwards, `None` once a sequence that
+    /// doesn't loop is over
+    pub fn next_frame(&self, frame: usize, reversed: bool) -> Option<(usize, bool)> {
+        let last = self.frames.len() - 1;
+        if self.ping_pong && last > 0 {
+            match (reversed, frame) {
+                (false, f) if f < last => Some((f + 1, false)),
+                (false, f) => Some((f - 1, true)),
+                (true, 0) if self.looping => Some((1, false)),
+                (true, 0) => None,
+                (true, f) => Some((f - 1, true)),
+            }
+        } else if frame < last {
+            Some((frame + 1, false))
+        } else if self.looping {
+            Some((0, false))
         } else {
-            frame + 1
+            None
         }
     }
 }
@@ -45,6 +211,14 @@ impl Default for AnimSequence {
         Self {
             frames: Vec::new(),
             looping: true,
+            mirror: None,
+            flip_x: false,
+            ping_pong: false,
+            hide_on_finished: false,
+            fps: None,
+            durations: Vec::new(),
+            events: Vec::new(),
+            on_finished: None,
         }
     }
 }
@@ -57,8 +231,42 @@ impl AssetLoader for AnimAssetLoader {
         Box::pin(async move {
             // random sleep for testing the loading screen
             //std::thread::sleep(std::time::Duration::from_millis((1+(rand::random::<u64>() % 24)) * 1000));
-            let loaded_data = serde_json::de::from_slice::<AnimAsset>(bytes)?;
-            load_context.set_default_asset(LoadedAsset::new(loaded_data));
+            let mut loaded_data = serde_json::de::from_slice::<AnimAsset>(bytes)?;
+            let mut problems = loaded_data.resolve_mirrors();
+
+            let atlas_len = match &loaded_data.atlas {
+                Some(atlas) => {
+                    let atlas_bytes = load_context
+                        .read_asset_bytes(atlas)
+                        .await
+                        .map_err(|err| anyhow::anyhow!("failed to read atlas {}: {}", atlas, err))?;
+                    Some(serde_json::de::from_slice::<AtlasDiskAsset>(&atlas_bytes)?.len())
+                }
+                None => None,
+            };
+
+            let graph = match &loaded_data.animgraph {
+                Some(animgraph) => {
+                    let graph_bytes = load_context
+                        .read_asset_bytes(animgraph)
+                        .await
+                        .map_err(|err| anyhow::anyhow!("failed to read animgraph {}: {}", animgraph, err))?;
+                    Some(serde_json::de::from_slice::<AnimGraphAsset>(&graph_bytes)?)
+                }
+                None => None,
+            };
+
+            problems.extend(loaded_data.validate(atlas_len, graph.as_ref()));
+            if !problems.is_empty() {
+                anyhow::bail!("invalid anim:\n{}", problems.join("\n"));
+            }
+
+            let graph_path = loaded_data
+                .animgraph
+                .as_deref()
+                .map(|animgraph| AssetPath::from(animgraph).to_owned());
+            loaded_data.graph = graph_path.clone().map(|graph_path| load_context.get_handle(graph_path));
+            load_context.set_default_asset(LoadedAsset::new(loaded_data).with_dependencies(graph_path.into_iter().collect()));
             Ok(())
         })
     }
@@ -68,11 +276,183 @@ impl AssetLoader for AnimAssetLoader {
     }
 }
 
+/// Tracks the animgraphs of every loaded anim, which finish loading after the anim does
+pub fn animgraphs_progress(
+    server: Res<AssetServer>,
+    errors: Res<LoadErrors>,
+    mut loading: ResMut<AssetsLoading>,
+    animations: Res<Assets<AnimAsset>>,
+    graphs: Res<Assets<AnimGraphAsset>>,
+) -> Progress {
+    use bevy::asset::LoadState;
+
+    let mut progress = Progress::default();
+    for graph in animations.iter().filter_map(|(_, anim)| anim.graph.as_ref()) {
+        progress.total += 1;
+        if graphs.contains(graph) {
+            progress.done += 1;
+        } else if server.get_load_state(graph) == LoadState::Failed {
+            loading.fail_load(graph, &server, &errors);
+            progress.done += 1;
+        }
+    }
+    progress
+}
+
 pub struct AnimAssetPlugin;
 
 impl Plugin for AnimAssetPlugin {
     fn build(&self, app: &mut App) {
         app.add_asset::<AnimAsset>();
-        app.init_asset_loader::<AnimAssetLoader>();
+        app.init_recording_asset_loader::<AnimAssetLoader>();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::assets::test_util::read_asset;
+
+    fn read_anim(name: &str) -> AnimAsset {
+        let mut anim: AnimAsset = read_asset(format!("anims/{}", name));
+        assert_eq!(anim.resolve_mirrors(), Vec::<String>::new(), "{}", name);
+        anim
+    }
+
+    fn atlas_len(anim: &AnimAsset) -> Option<usize> {
+        Some(read_asset::<AtlasDiskAsset>(anim.atlas.as_ref()?).len())
+    }
+
+    fn graph(anim: &AnimAsset) -> Option<AnimGraphAsset> {
+        Some(read_asset(anim.animgraph.as_ref()?))
+    }
+
+    #[test]
+    fn shipped_anims_are_valid() {
+        for name in ["brick.anim", "fire.anim", "guard.anim", "runner.anim"] {
+            let anim = read_anim(name);
+            assert!(anim.atlas.is_some(), "{} has no atlas", name);
+            assert_eq!(
+                anim.validate(atlas_len(&anim), graph(&anim).as_ref()),
+                Vec::<String>::new(),
+                "{}",
+                name
+            );
+        }
+    }
+
+    #[test]
+    fn reports_missing_sequences_and_bad_frames() {
+        let mut anim = read_anim("guard.anim");
+        anim.sequence.remove("respawn");
+        anim.sequence.get_mut("runLeft").unwrap().frames.push(40);
+        anim.sequence.get_mut("fallLeft").unwrap().frames.clear();
+        anim.sequence.get_mut("fallRight").unwrap().on_finished = Some("explode".to_owned());
+
+        assert_eq!(
+            anim.validate(atlas_len(&anim), graph(&anim).as_ref()),
+            vec![
+                "sequence \"fallLeft\" has no frames",
+                "sequence \"fallRight\" sends \"explode\", which nothing listens for",
+                "sequence \"runLeft\" uses frame 40, but the atlas only has 22 frames",
+                "animgraph \"animgraphs/guard.animgraph\" needs a \"respawn\" sequence",
+            ]
+        );
+    }
+
+    // atlas frame shown on each tick of a sequence, for at most `max_ticks`
+    fn ticks(sequence: &AnimSequence, max_ticks: usize) -> Vec<usize> {
+        let mut ticks = Vec::new();
+        let mut next = Some((0, false));
+        while let Some((frame, reversed)) = next {
+            let duration = sequence.frame_time(frame, 1.0) as usize;
+            ticks.resize(ticks.len() + duration, sequence.frames[frame]);
+            if ticks.len() >= max_ticks {
+                ticks.truncate(max_ticks);
+                break;
+            }
+            next = sequence.next_frame(frame, reversed);
+        }
+        ticks
+    }
+
+    #[test]
+    fn durations_play_like_repeated_frames() {
+        let guard = read_anim("guard.anim");
+        let mut stunned = vec![8; 26];
+        stunned.extend([9, 9, 10, 10, 9, 9, 10, 10, 8, 8]);
+        assert_eq!(ticks(&guard.sequence["stunnedRight"], 100), stunned);
+        assert_eq!(ticks(&guard.sequence["respawn"], 100), [17, 17, 17, 17, 17, 17, 18, 18, 18, 18, 0]);
+        assert_eq!(ticks(&guard.sequence["ropeRight"], 10), [11, 12, 12, 13, 13, 11, 12, 12, 13, 13]);
+
+        let runner = read_anim("runner.anim");
+        assert_eq!(
+            ticks(&runner.sequence["digRight"], 100),
+            [15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 0]
+        );
+
+        // the fire effects used to be despawned after half a second, now it's once they're over
+        let fire = read_anim("fire.anim");
+        for sequence in fire.sequence.values() {
+            let seconds: f32 = (0..sequence.frames.len()).map(|frame| sequence.frame_time(frame, fire.fps)).sum();
+            assert!((seconds - 0.5).abs() < 0.001, "fire lasts {}s", seconds);
+        }
+    }
+
+    #[test]
+    fn ping_pong_bounces_between_the_ends() {
+        let mut sequence = AnimSequence {
+            frames: vec![0, 1, 2],
+            ping_pong: true,
+            ..Default::default()
+        };
+        assert_eq!(ticks(&sequence, 9), [0, 1, 2, 1, 0, 1, 2, 1, 0]);
+
+        sequence.looping = false;
+        assert_eq!(ticks(&sequence, 100), [0, 1, 2, 1, 0]);
+    }
+
+    #[test]
+    fn omitted_fields_are_the_default() {
+        let sequence: AnimSequence = serde_json::from_str(r#"{ "frames": [0, 1] }"#).unwrap();
+        assert_eq!(sequence.looping, AnimSequence::default().looping);
+    }
+
+    #[test]
+    fn sequence_fps_overrides_the_anim() {
+        let sequence = AnimSequence {
+            frames: vec![0, 1],
+            fps: Some(10.0),
+            durations: vec![3.0],
+            ..Default::default()
+        };
+        assert_eq!(sequence.frame_time(0, 22.0), 0.3);
+        assert_eq!(sequence.frame_time(1, 22.0), 0.1);
+    }
+
+    #[test]
+    fn mirrors_copy_their_source_flipped() {
+        let runner = read_anim("runner.anim");
+        let dig = &runner.sequence["digLeft"];
+        assert!(dig.flip_x);
+        assert_eq!(dig.frames, runner.sequence["digRight"].frames);
+        assert_eq!(dig.events_on(1).collect::<Vec<_>>(), ["digFinished"]);
+        assert!(!runner.sequence["digRight"].flip_x);
+
+        let mut anim = AnimAsset::default();
+        let mirror = |name: &str| AnimSequence {
+            frames: Vec::new(),
+            mirror: Some(name.to_owned()),
+            ..Default::default()
+        };
+        anim.sequence.insert("left".to_owned(), mirror("right"));
+        anim.sequence.insert("up".to_owned(), mirror("left"));
+        assert_eq!(
+            anim.resolve_mirrors(),
+            vec![
+                "sequence \"left\" mirrors \"right\", which doesn't exist",
+                "sequence \"up\" mirrors \"left\", which is a mirror itself",
+            ]
+        );
     }
 }
@@ -0,0 +1,134 @@
+use bevy::{
+    asset::{AssetLoader, BoxedFuture, LoadContext, LoadedAsset},
+    prelude::*,
+    reflect::TypeUuid,
+};
+use serde::Deserialize;
+
+use super::AssetLoading::RecordLoadErrors;
+
+/// Picks which sequence of an anim plays from what's going on with the entity, so new characters can be
+/// animated without writing a system for them
+#[derive(Debug, TypeUuid, Deserialize)]
+#[uuid = "b7d2e4f1-5a3c-4e9b-8d6f-1c0a2b3e4d5f"]
+pub struct AnimGraphAsset {
+    /// Sequence to start with, if the entity isn't spawned with one
+    #[serde(default)]
+    pub initial: Option<String>,
+    /// The animation only runs while one of these holds, it always runs when empty
+    #[serde(default)]
+    pub active_when: Vec<AnimCondition>,
+    /// Checked in order, the first state whose conditions all hold gets played. When none do the current
+    /// sequence keeps playing
+    pub states: Vec<AnimGraphState>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AnimGraphState {
+    pub sequence: String,
+    #[serde(default)]
+    pub when: Vec<AnimCondition>,
+}
+
+/// Things an animgraph can check, anything an entity doesn't have the components for never holds
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum AnimCondition {
+    Moving,
+    MovingLeft,
+    MovingRight,
+    MovingVertically,
+    Falling,
+    /// The last fall was to the left, which is also the side a stunned guard fell in from
+    FallingLeft,
+    OnRope,
+    /// A runner digging a hole
+    Burning,
+    BurningLeft,
+    Stunned,
+    /// Killed, waiting to come back
+    WaitingToRespawn,
+    /// Showing up at the respawn point
+    Respawning,
+    BrickBurning,
+    BrickRebuilding,
+    BrickNotBurning,
+}
+
+impl AnimGraphAsset {
+    pub fn is_active(&self, holds: impl Fn(AnimCondition) -> bool) -> bool {
+        self.active_when.is_empty() || self.active_when.iter().any(|condition| holds(*condition))
+    }
+
+    /// Sequence of the first state that applies
+    pub fn sequence(&self, holds: impl Fn(AnimCondition) -> bool) -> Option<&str> {
+        self.states
+            .iter()
+            .find(|state| state.when.iter().all(|condition| holds(*condition)))
+            .map(|state| state.sequence.as_str())
+    }
+
+    /// Every sequence the graph can switch to, which the anim it drives needs to have
+    pub fn sequences(&self) -> Vec<&str> {
+        let mut sequences: Vec<&str> = self.initial.iter().map(String::as_str).collect();
+        for state in &self.states {
+            if !sequences.contains(&state.sequence.as_str()) {
+                sequences.push(&state.sequence);
+            }
+        }
+        sequences
+    }
+}
+
+#[derive(Default)]
+pub struct AnimGraphAssetLoader;
+
+impl AssetLoader for AnimGraphAssetLoader {
+    fn load<'a>(&'a self, bytes: &'a [u8], load_context: &'a mut LoadContext) -> BoxedFuture<'a, Result<(), anyhow::Error>> {
+        Box::pin(async move {
+            let loaded_data = serde_json::de::from_slice::<AnimGraphAsset>(bytes)?;
+            load_context.set_default_asset(LoadedAsset::new(loaded_data));
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["animgraph"]
+    }
+}
+
+pub struct AnimGraphAssetPlugin;
+
+impl Plugin for AnimGraphAssetPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_asset::<AnimGraphAsset>();
+        app.init_recording_asset_loader::<AnimGraphAssetLoader>();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::assets::test_util::read_asset;
+    use AnimCondition::*;
+
+    fn read_graph(name: &str) -> AnimGraphAsset {
+        read_asset(format!("animgraphs/{}", name))
+    }
+
+    #[test]
+    fn first_matching_state_wins() {
+        let runner = read_graph("runner.animgraph");
+        let when = |conditions: &[AnimCondition]| runner.sequence(|condition| conditions.contains(&condition));
+
+        assert_eq!(when(&[Burning, BurningLeft, MovingRight]), Some("digLeft"));
+        assert_eq!(when(&[Burning, MovingRight]), Some("digRight"));
+        assert_eq!(when(&[Falling, FallingLeft, OnRope, MovingLeft]), Some("fallLeft"));
+        assert_eq!(when(&[OnRope, MovingLeft]), Some("ropeLeft"));
+        assert_eq!(when(&[OnRope]), None);
+
+        assert!(runner.is_active(|condition| condition == Burning));
+        assert!(!runner.is_active(|condition| condition == OnRope));
+        assert!(read_graph("brick.animgraph").is_active(|_| false));
+    }
+}
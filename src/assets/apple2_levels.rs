@@ -0,0 +1,169 @@
+use bevy::{
+    asset::{AssetLoader, LoadContext},
+    prelude::*,
+    utils::BoxedFuture,
+};
+
+use super::level_pack::LevelPackHeader;
+use super::level_validation::{LevelDiagnosticKind, LevelValidation};
+use super::LevelAsset::{set_level_pack, LevelDataAsset, LevelTile, TileType};
+
+/// Levels on the original disk are always 28x16
+pub const APPLE2_LEVEL_WIDTH: i32 = 28;
+pub const APPLE2_LEVEL_HEIGHT: i32 = 16;
+
+/// Each level takes up a full sector, even though the tiles only need 224 bytes of it
+pub const APPLE2_SECTOR_SIZE: usize = 256;
+
+/// Maps the tile codes used by the original game onto our tiles.
+///
+/// `Some(None)` is an empty tile, `None` means the code isn't one the original game uses
+pub fn apple2_tile(code: u8) -> Option<Option<TileType>> {
+    match code {
+        0 => Some(None),
+        1 => Some(Some(TileType::Brick)),
+        2 => Some(Some(TileType::SolidBrick)),
+        3 => Some(Some(TileType::Ladder)),
+        4 => Some(Some(TileType::Rope)),
+        5 => Some(Some(TileType::FalseBrick)),
+        6 => Some(Some(TileType::HiddenLadder)),
+        7 => Some(Some(TileType::Gold)),
+        8 => Some(Some(TileType::Guard)),
+        9 => Some(Some(TileType::Player)),
+        _ => None,
+    }
+}
+
+/// Decodes level data as laid out on the original Apple II disk: one level per 256 byte sector,
+/// with the tiles stored row by row from the top, two to a byte.  The low nibble holds the left tile
+/// of each pair, and the last 32 bytes of each sector are unused.
+pub fn decode_apple2_levels(bytes: &[u8]) -> Result<Vec<(LevelDataAsset, LevelValidation)>, anyhow::Error> {
+    if bytes.is_empty() || bytes.len() % APPLE2_SECTOR_SIZE != 0 {
+        anyhow::bail!(
+            "apple ii level data should be a multiple of {} bytes, found {}",
+            APPLE2_SECTOR_SIZE,
+            bytes.len()
+        );
+    }
+
+    Ok(bytes.chunks(APPLE2_SECTOR_SIZE).map(decode_apple2_level).collect())
+}
+
+fn decode_apple2_level(sector: &[u8]) -> (LevelDataAsset, LevelValidation) {
+    let mut level_data = LevelDataAsset::new();
+    level_data.width = APPLE2_LEVEL_WIDTH;
+    level_data.height = APPLE2_LEVEL_HEIGHT;
+
+    let mut validation = LevelValidation::default();
+    let tile_count = (APPLE2_LEVEL_WIDTH * APPLE2_LEVEL_HEIGHT) as usize;
+    for index in 0..tile_count {
+        let byte = sector[index / 2];
+        let code = if index % 2 == 0 { byte & 0x0f } else { byte >> 4 };
+
+        let row = index / APPLE2_LEVEL_WIDTH as usize;
+        let column = index % APPLE2_LEVEL_WIDTH as usize;
+        match apple2_tile(code) {
+            Some(Some(tile_type)) => {
+                let p = IVec2::new(column as i32, APPLE2_LEVEL_HEIGHT - 1 - row as i32);
+                level_data.tiles.push(LevelTile::new(tile_type, p));
+            }
+            Some(None) => {}
+            None => validation.push(LevelDiagnosticKind::UnknownTileCode { code }, Some(row), Some(column)),
+        }
+    }
+
+    validation.check_level(&level_data);
+    (level_data, validation)
+}
+
+/// Loads a dump of the original levels, each level is available as a labelled asset
+/// numbered from one, ie. `lode_runner.a2levels#017`
+#[derive(Default)]
+pub struct Apple2LevelsAssetLoader;
+
+impl AssetLoader for Apple2LevelsAssetLoader {
+    fn load<'a>(&'a self, bytes: &'a [u8], load_context: &'a mut LoadContext) -> BoxedFuture<'a, Result<(), anyhow::Error>> {
+        Box::pin(async move { set_level_pack(load_context, LevelPackHeader::default(), decode_apple2_levels(bytes)?) })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["a2levels"]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::assets::level_validation::LevelDiagnostic;
+
+    const FIXTURE: &[u8] = include_bytes!("../../tests/fixtures/apple2.a2levels");
+
+    fn tile_at(level: &LevelDataAsset, x: i32, y: i32) -> Option<TileType> {
+        level.tiles.iter().find(|t| t.position == IVec2::new(x, y)).map(|t| t.behaviour)
+    }
+
+    #[test]
+    fn decodes_every_sector() {
+        let levels = decode_apple2_levels(FIXTURE).unwrap();
+        assert_eq!(levels.len(), 2);
+        for (level, _) in &levels {
+            assert_eq!(level.width, APPLE2_LEVEL_WIDTH);
+            assert_eq!(level.height, APPLE2_LEVEL_HEIGHT);
+        }
+    }
+
+    #[test]
+    fn maps_tile_codes() {
+        let levels = decode_apple2_levels(FIXTURE).unwrap();
+        let (level, validation) = &levels[0];
+        assert!(!validation.has_errors(), "{:?}", validation.diagnostics);
+
+        // top row, hidden ladders in both corners
+        assert_eq!(tile_at(level, 0, 15), Some(TileType::HiddenLadder));
+        assert_eq!(tile_at(level, 27, 15), Some(TileType::HiddenLadder));
+        assert_eq!(tile_at(level, 1, 15), None);
+
+        // gold and a guard on the next row down
+        assert_eq!(tile_at(level, 4, 14), Some(TileType::Gold));
+        assert_eq!(tile_at(level, 14, 14), Some(TileType::Guard));
+
+        // bricks, with trap bricks and a ladder
+        assert_eq!(tile_at(level, 0, 13), Some(TileType::Brick));
+        assert_eq!(tile_at(level, 4, 13), Some(TileType::FalseBrick));
+        assert_eq!(tile_at(level, 5, 13), Some(TileType::FalseBrick));
+        assert_eq!(tile_at(level, 9, 13), Some(TileType::Ladder));
+
+        // rope between the ladders
+        assert_eq!(tile_at(level, 10, 12), Some(TileType::Rope));
+        assert_eq!(tile_at(level, 16, 12), Some(TileType::Rope));
+
+        // player, and the solid floor
+        assert_eq!(tile_at(level, 16, 1), Some(TileType::Player));
+        for x in 0..APPLE2_LEVEL_WIDTH {
+            assert_eq!(tile_at(level, x, 0), Some(TileType::SolidBrick));
+        }
+    }
+
+    #[test]
+    fn reports_unknown_tile_codes() {
+        let levels = decode_apple2_levels(FIXTURE).unwrap();
+        let (level, validation) = &levels[1];
+
+        assert_eq!(
+            validation.diagnostics,
+            vec![LevelDiagnostic {
+                kind: LevelDiagnosticKind::UnknownTileCode { code: 0xc },
+                row: Some(1),
+                column: Some(21),
+            }]
+        );
+        assert_eq!(tile_at(level, 21, 14), None);
+        assert_eq!(tile_at(level, 1, 1), Some(TileType::Player));
+    }
+
+    #[test]
+    fn rejects_partial_sectors() {
+        assert!(decode_apple2_levels(&FIXTURE[..300]).is_err());
+        assert!(decode_apple2_levels(&[]).is_err());
+    }
+}
@@ -0,0 +1,136 @@
+use bevy::{
+    asset::{AssetLoader, AssetPath, LoadContext, LoadedAsset},
+    prelude::*,
+    reflect::TypeUuid,
+    utils::BoxedFuture,
+};
+use serde::Deserialize;
+use std::collections::HashMap;
+
+use super::AssetLoading::RecordLoadErrors;
+use super::AssetLoading::{AssetsLoading, LoadErrors, Progress};
+
+/// A sprite sheet laid out as a grid, with names for the frames gameplay code needs to refer to
+#[derive(Debug, TypeUuid)]
+#[uuid = "6a3b7c1e-2d4f-4e8a-9b0c-5f1e2d3c4b5a"]
+pub struct AtlasAsset {
+    pub texture: Handle<Image>,
+    pub texture_atlas: Handle<TextureAtlas>,
+    pub frames: HashMap<String, usize>,
+    /// Number of cells in the grid
+    pub len: usize,
+}
+
+impl AtlasAsset {
+    /// Index of a named frame, if the atlas has it
+    pub fn frame(&self, name: &str) -> Option<usize> {
+        self.frames.get(name).copied()
+    }
+
+    pub fn sprite(&self, name: &str) -> Option<TextureAtlasSprite> {
+        self.frame(name).map(TextureAtlasSprite::new)
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AtlasDiskAsset {
+    /// Image path, relative to the assets folder like every other path in an asset
+    pub image: String,
+    pub tile_width: f32,
+    pub tile_height: f32,
+    pub columns: usize,
+    pub rows: usize,
+    /// Space between cells
+    #[serde(default)]
+    pub padding_width: f32,
+    #[serde(default)]
+    pub padding_height: f32,
+    #[serde(default)]
+    pub frames: HashMap<String, usize>,
+}
+
+impl AtlasDiskAsset {
+    /// Number of cells in the grid
+    pub fn len(&self) -> usize {
+        self.columns * self.rows
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+#[derive(Default)]
+pub struct AtlasAssetLoader;
+
+impl AssetLoader for AtlasAssetLoader {
+    fn load<'a>(&'a self, bytes: &'a [u8], load_context: &'a mut LoadContext) -> BoxedFuture<'a, Result<(), anyhow::Error>> {
+        Box::pin(async move {
+            let loaded_data = serde_json::de::from_slice::<AtlasDiskAsset>(bytes)?;
+
+            let len = loaded_data.len();
+            for (name, index) in &loaded_data.frames {
+                if *index >= len {
+                    anyhow::bail!("frame {:?} is {}, but the atlas only has {} frames", name, index, len);
+                }
+            }
+
+            let image_path = AssetPath::from(loaded_data.image.as_str()).to_owned();
+            let texture: Handle<Image> = load_context.get_handle(image_path.clone());
+            let texture_atlas = TextureAtlas::from_grid_with_padding(
+                texture.clone(),
+                Vec2::new(loaded_data.tile_width, loaded_data.tile_height),
+                loaded_data.columns,
+                loaded_data.rows,
+                Vec2::new(loaded_data.padding_width, loaded_data.padding_height),
+            );
+            let texture_atlas =
+                load_context.set_labeled_asset("texture_atlas", LoadedAsset::new(texture_atlas).with_dependency(image_path));
+
+            load_context.set_default_asset(LoadedAsset::new(AtlasAsset {
+                texture,
+                texture_atlas,
+                frames: loaded_data.frames,
+                len,
+            }));
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["atlas"]
+    }
+}
+
+/// Tracks the images behind every loaded atlas, the atlas itself finishes loading before its image does
+pub fn atlas_images_progress(
+    server: Res<AssetServer>,
+    errors: Res<LoadErrors>,
+    mut loading: ResMut<AssetsLoading>,
+    atlases: Res<Assets<AtlasAsset>>,
+) -> Progress {
+    use bevy::asset::LoadState;
+
+    let mut progress = Progress::default();
+    for (_, atlas) in atlases.iter() {
+        progress.total += 1;
+        match server.get_load_state(&atlas.texture) {
+            LoadState::Loading | LoadState::NotLoaded => {}
+            LoadState::Failed => {
+                loading.fail_load(&atlas.texture, &server, &errors);
+                progress.done += 1;
+            }
+            _ => progress.done += 1,
+        }
+    }
+    progress
+}
+
+pub struct AtlasAssetPlugin;
+
+impl Plugin for AtlasAssetPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_asset::<AtlasAsset>();
+        app.init_recording_asset_loader::<AtlasAssetLoader>();
+    }
+}
@@ -0,0 +1,82 @@
+use bevy::{
+    asset::{AssetIo, AssetIoError},
+    utils::{BoxedFuture, HashMap},
+};
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+
+/// Every file in `assets/` as (path, contents), generated by `build.rs`
+static EMBEDDED_ASSETS: &[(&str, &[u8])] = include!(concat!(env!("OUT_DIR"), "/embedded_assets.rs"));
+
+/// The `assets/` folder built into the executable, so a build runs from anywhere
+pub struct EmbeddedAssetIo {
+    files: HashMap<PathBuf, &'static [u8]>,
+}
+
+impl EmbeddedAssetIo {
+    pub fn new() -> Self {
+        Self {
+            files: EMBEDDED_ASSETS.iter().map(|(path, bytes)| (PathBuf::from(path), *bytes)).collect(),
+        }
+    }
+}
+
+impl Default for EmbeddedAssetIo {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl AssetIo for EmbeddedAssetIo {
+    fn load_path<'a>(&'a self, path: &'a Path) -> BoxedFuture<'a, Result<Vec<u8>, AssetIoError>> {
+        Box::pin(async move {
+            match self.files.get(path) {
+                Some(bytes) => Ok(bytes.to_vec()),
+                None => Err(AssetIoError::NotFound(path.to_owned())),
+            }
+        })
+    }
+
+    fn read_directory(&self, path: &Path) -> Result<Box<dyn Iterator<Item = PathBuf>>, AssetIoError> {
+        // only files are stored, folders are whatever comes right after `path` in their paths
+        let entries: BTreeSet<PathBuf> = self
+            .files
+            .keys()
+            .filter_map(|file| Some(path.join(file.strip_prefix(path).ok()?.components().next()?)))
+            .collect();
+        if entries.is_empty() {
+            return Err(AssetIoError::NotFound(path.to_owned()));
+        }
+        Ok(Box::new(entries.into_iter()))
+    }
+
+    fn is_directory(&self, path: &Path) -> bool {
+        self.files.keys().any(|file| file.starts_with(path) && file != path)
+    }
+
+    fn watch_path_for_changes(&self, _path: &Path) -> Result<(), AssetIoError> {
+        Ok(())
+    }
+
+    fn watch_for_changes(&self) -> Result<(), AssetIoError> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lists_embedded_folders() {
+        let embedded = EmbeddedAssetIo::new();
+        assert!(embedded.is_directory(Path::new("levels")));
+        assert!(!embedded.is_directory(Path::new("hole.atlas")));
+
+        let levels: Vec<PathBuf> = embedded.read_directory(Path::new("levels")).unwrap().collect();
+        assert!(levels.contains(&PathBuf::from("levels/classic")));
+        let root: Vec<PathBuf> = embedded.read_directory(Path::new("")).unwrap().collect();
+        assert!(root.contains(&PathBuf::from("hole.atlas")));
+        assert!(embedded.read_directory(Path::new("missing")).is_err());
+    }
+}
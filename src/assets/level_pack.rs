@@ -0,0 +1,160 @@
+use bevy::{
+    asset::{AssetLoader, LoadContext},
+    prelude::*,
+    reflect::TypeUuid,
+    utils::BoxedFuture,
+};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+use super::apple2_levels::decode_apple2_levels;
+use super::level_validation::LevelValidation;
+use super::lrtr_levels::parse_lrtr_levels;
+use super::LevelAsset::{decode_level, level_label, set_level_pack, LevelDataAsset, LevelDataDiskAsset};
+
+/// Newest level pack format this build understands
+pub const LEVEL_PACK_FORMAT_VERSION: u32 = 1;
+
+/// Any file holding several levels, each level is also available as a labelled asset numbered
+/// from one, ie. `levels/classic.lrpack#017`
+#[derive(Debug, TypeUuid)]
+#[uuid = "0c3a1b5e-5f0e-4d8b-9d0b-6f1f7f4f2a61"]
+pub struct LevelPackAsset {
+    pub header: LevelPackHeader,
+    /// Levels in pack order, `levels[0]` is `#001`
+    pub levels: Vec<Handle<LevelDataAsset>>,
+}
+
+/// Descriptive information about a level pack
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct LevelPackHeader {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub author: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub notes: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct LevelPackDiskAsset {
+    #[serde(default = "LevelPackDiskAsset::default_version")]
+    pub version: u32,
+    #[serde(default, skip_serializing_if = "LevelPackDiskAsset::is_default_header")]
+    pub header: LevelPackHeader,
+    /// Every level in the pack, in the same format as a `.level` file
+    pub levels: Vec<LevelDataDiskAsset>,
+}
+
+impl LevelPackDiskAsset {
+    fn default_version() -> u32 {
+        LEVEL_PACK_FORMAT_VERSION
+    }
+
+    fn is_default_header(header: &LevelPackHeader) -> bool {
+        *header == LevelPackHeader::default()
+    }
+}
+
+/// Parses the contents of a `.lrpack` file, along with anything validation had to say about each level
+pub fn parse_level_pack(bytes: &[u8]) -> Result<(LevelPackHeader, Vec<(LevelDataAsset, LevelValidation)>), anyhow::Error> {
+    let loaded_data = serde_json::de::from_slice::<LevelPackDiskAsset>(bytes)?;
+    if loaded_data.version > LEVEL_PACK_FORMAT_VERSION {
+        anyhow::bail!(
+            "level pack format version {} is newer than the supported version {}",
+            loaded_data.version,
+            LEVEL_PACK_FORMAT_VERSION
+        );
+    }
+    if loaded_data.levels.is_empty() {
+        anyhow::bail!("level pack doesn't contain any levels");
+    }
+
+    let mut levels = Vec::new();
+    for (index, level) in loaded_data.levels.into_iter().enumerate() {
+        let level = decode_level(level).map_err(|err| anyhow::anyhow!("level #{}: {}", level_label(index), err))?;
+        levels.push(level);
+    }
+    Ok((loaded_data.header, levels))
+}
+
+/// Writes levels out as a `.lrpack`, in the order given
+pub fn write_level_pack(header: &LevelPackHeader, levels: &[LevelDataAsset]) -> Result<String, anyhow::Error> {
+    let disk_asset = LevelPackDiskAsset {
+        version: LEVEL_PACK_FORMAT_VERSION,
+        header: header.clone(),
+        levels: levels.iter().map(|level| level.to_disk()).collect(),
+    };
+
+    let mut bytes = Vec::new();
+    let formatter = serde_json::ser::PrettyFormatter::with_indent(b"\t");
+    let mut serializer = serde_json::Serializer::with_formatter(&mut bytes, formatter);
+    disk_asset.serialize(&mut serializer)?;
+    Ok(String::from_utf8(bytes)?)
+}
+
+#[derive(Default)]
+pub struct LevelPackAssetLoader;
+
+impl AssetLoader for LevelPackAssetLoader {
+    fn load<'a>(&'a self, bytes: &'a [u8], load_context: &'a mut LoadContext) -> BoxedFuture<'a, Result<(), anyhow::Error>> {
+        Box::pin(async move {
+            let (header, levels) = parse_level_pack(bytes)?;
+            set_level_pack(load_context, header, levels)
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["lrpack"]
+    }
+}
+
+/// Number of levels in a pack in any of the formats there's a loader for, without adding them as assets
+pub fn count_pack_levels(path: &Path, bytes: &[u8]) -> Result<usize, anyhow::Error> {
+    match path.extension().and_then(|ext| ext.to_str()) {
+        Some("lrpack") => Ok(parse_level_pack(bytes)?.1.len()),
+        Some("a2levels") => Ok(decode_apple2_levels(bytes)?.len()),
+        Some("lrtr") => Ok(parse_lrtr_levels(std::str::from_utf8(bytes)?)?.len()),
+        _ => anyhow::bail!("{} isn't a level pack", path.display()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::assets::test_util::{asset_files, read_asset_bytes};
+    use crate::assets::LevelAsset::parse_level;
+
+    #[test]
+    fn classic_levels_pack_round_trip() {
+        let levels: Vec<LevelDataAsset> = asset_files("levels/classic", "level")
+            .iter()
+            .map(|path| parse_level(&read_asset_bytes(path)).unwrap().0)
+            .collect();
+
+        let header = LevelPackHeader {
+            name: Some("Classic".to_owned()),
+            ..Default::default()
+        };
+        let written = write_level_pack(&header, &levels).unwrap();
+        let (reloaded_header, reloaded) = parse_level_pack(written.as_bytes()).unwrap();
+        assert_eq!(
+            count_pack_levels(Path::new("classic.lrpack"), written.as_bytes()).unwrap(),
+            levels.len()
+        );
+
+        assert_eq!(header, reloaded_header);
+        assert_eq!(levels.len(), reloaded.len());
+        for (level, (reloaded, _)) in levels.iter().zip(reloaded.iter()) {
+            assert_eq!(level, reloaded);
+        }
+    }
+
+    #[test]
+    fn reports_the_broken_level() {
+        let pack = r#"{ "levels": [ { "rows": ["&$", "@@"] }, { "version": 99, "rows": [] } ] }"#;
+        let err = parse_level_pack(pack.as_bytes()).unwrap_err();
+        assert!(err.to_string().starts_with("level #002:"), "{}", err);
+    }
+}
@@ -0,0 +1,298 @@
+use crate::assets::LevelAsset::{LevelDataAsset, TileType};
+use bevy::math::IVec2;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fmt;
+
+/// Largest level the game is willing to load
+pub const MAX_LEVEL_WIDTH: i32 = 128;
+pub const MAX_LEVEL_HEIGHT: i32 = 64;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Severity {
+    Warning,
+    Error,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum LevelDiagnosticKind {
+    BadDimensions { width: i32, height: i32 },
+    MissingPlayer,
+    MultiplePlayers { count: usize },
+    RaggedRow { expected: i32, found: i32 },
+    RowCount { expected: i32, found: i32 },
+    Oversize { width: i32, height: i32 },
+    UnknownGlyph { glyph: char },
+    UnknownTileCode { code: u8 },
+    NoGold,
+    UnreachableGold,
+}
+
+impl LevelDiagnosticKind {
+    pub fn severity(&self) -> Severity {
+        use LevelDiagnosticKind::*;
+        match self {
+            // anything that doesn't fit in the level gets dropped, shorter rows are just padded out
+            RaggedRow { expected, found } | RowCount { expected, found } if found > expected => Severity::Error,
+            RaggedRow { .. } | RowCount { .. } => Severity::Warning,
+            BadDimensions { .. } | MissingPlayer | MultiplePlayers { .. } | Oversize { .. } => Severity::Error,
+            UnknownGlyph { .. } | UnknownTileCode { .. } | NoGold | UnreachableGold => Severity::Warning,
+        }
+    }
+}
+
+impl fmt::Display for LevelDiagnosticKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use LevelDiagnosticKind::*;
+        match self {
+            BadDimensions { width, height } => write!(f, "level is {}x{}, it needs at least one tile", width, height),
+            MissingPlayer => write!(f, "level has no player"),
+            MultiplePlayers { count } => write!(f, "level has {} players, expected exactly one", count),
+            RaggedRow { expected, found } => write!(f, "row is {} tiles wide, expected {}", found, expected),
+            RowCount { expected, found } => write!(f, "level has {} rows, expected {}", found, expected),
+            Oversize { width, height } => write!(
+                f,
+                "level is {}x{}, larger than the maximum of {}x{}",
+                width, height, MAX_LEVEL_WIDTH, MAX_LEVEL_HEIGHT
+            ),
+            UnknownGlyph { glyph } => write!(f, "unknown tile glyph {:?}", glyph),
+            UnknownTileCode { code } => write!(f, "unknown tile code {:#x}", code),
+            NoGold => write!(f, "level has no gold"),
+            UnreachableGold => write!(f, "gold can't be reached from the player start"),
+        }
+    }
+}
+
+/// A single problem found in a level, with the (zero based) row and column it was found at, if any.
+///
+/// Rows are counted from the top, in the same order they're listed in the file.
+#[derive(Clone, Debug, PartialEq)]
+pub struct LevelDiagnostic {
+    pub kind: LevelDiagnosticKind,
+    pub row: Option<usize>,
+    pub column: Option<usize>,
+}
+
+impl LevelDiagnostic {
+    pub fn severity(&self) -> Severity {
+        self.kind.severity()
+    }
+
+    /// Formats the diagnostic as `path:row:column: severity: message`
+    pub fn report(&self, path: impl fmt::Display) -> String {
+        match self.row {
+            Some(_) => format!("{}:{}", path, self),
+            None => format!("{}: {}", path, self),
+        }
+    }
+}
+
+impl fmt::Display for LevelDiagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let severity = match self.severity() {
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        };
+        match (self.row, self.column) {
+            (Some(row), Some(column)) => write!(f, "{}:{}: {}: {}", row + 1, column + 1, severity, self.kind),
+            (Some(row), None) => write!(f, "{}: {}: {}", row + 1, severity, self.kind),
+            _ => write!(f, "{}: {}", severity, self.kind),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct LevelValidation {
+    pub diagnostics: Vec<LevelDiagnostic>,
+}
+
+impl LevelValidation {
+    pub fn push(&mut self, kind: LevelDiagnosticKind, row: Option<usize>, column: Option<usize>) {
+        self.diagnostics.push(LevelDiagnostic { kind, row, column });
+    }
+
+    pub fn has_errors(&self) -> bool {
+        self.errors().next().is_some()
+    }
+
+    pub fn errors(&self) -> impl Iterator<Item = &LevelDiagnostic> {
+        self.diagnostics.iter().filter(|d| d.severity() == Severity::Error)
+    }
+
+    pub fn warnings(&self) -> impl Iterator<Item = &LevelDiagnostic> {
+        self.diagnostics.iter().filter(|d| d.severity() == Severity::Warning)
+    }
+
+    /// Checks the raw rows of a level against the size it will be loaded at
+    pub fn check_rows(&mut self, rows: &[String], width: i32, height: i32, glyphs: fn(char) -> Option<TileType>) {
+        if rows.len() as i32 != height {
+            self.push(
+                LevelDiagnosticKind::RowCount {
+                    expected: height,
+                    found: rows.len() as i32,
+                },
+                None,
+                None,
+            );
+        }
+
+        for (row, row_data) in rows.iter().enumerate() {
+            let row_width = row_data.chars().count() as i32;
+            if row_width != width {
+                self.push(
+                    LevelDiagnosticKind::RaggedRow {
+                        expected: width,
+                        found: row_width,
+                    },
+                    Some(row),
+                    None,
+                );
+            }
+
+            for (column, glyph) in row_data.chars().enumerate() {
+                if glyph != ' ' && glyphs(glyph).is_none() {
+                    self.push(LevelDiagnosticKind::UnknownGlyph { glyph }, Some(row), Some(column));
+                }
+            }
+        }
+    }
+
+    /// Checks that a loaded level is something the game can actually play
+    pub fn check_level(&mut self, level: &LevelDataAsset) {
+        if level.width <= 0 || level.height <= 0 {
+            self.push(
+                LevelDiagnosticKind::BadDimensions {
+                    width: level.width,
+                    height: level.height,
+                },
+                None,
+                None,
+            );
+            return;
+        }
+
+        if level.width > MAX_LEVEL_WIDTH || level.height > MAX_LEVEL_HEIGHT {
+            self.push(
+                LevelDiagnosticKind::Oversize {
+                    width: level.width,
+                    height: level.height,
+                },
+                None,
+                None,
+            );
+            // the remaining checks get expensive on huge levels, and the level is unusable anyways
+            return;
+        }
+
+        let to_row = |pos: IVec2| Some((level.height - 1 - pos.y) as usize);
+        let to_column = |pos: IVec2| Some(pos.x as usize);
+
+        let players: Vec<IVec2> = level
+            .tiles
+            .iter()
+            .filter(|t| t.behaviour == TileType::Player)
+            .map(|t| t.position)
+            .collect();
+        match players.len() {
+            0 => self.push(LevelDiagnosticKind::MissingPlayer, None, None),
+            1 => {}
+            count => {
+                for player in &players[1..] {
+                    self.push(LevelDiagnosticKind::MultiplePlayers { count }, to_row(*player), to_column(*player));
+                }
+            }
+        }
+
+        let gold: Vec<IVec2> = level
+            .tiles
+            .iter()
+            .filter(|t| t.behaviour == TileType::Gold)
+            .map(|t| t.position)
+            .collect();
+        if gold.is_empty() {
+            self.push(LevelDiagnosticKind::NoGold, None, None);
+        }
+
+        if let Some(player) = players.first() {
+            let reachable = reachable_from(level, *player);
+            for treasure in gold.iter().filter(|p| !reachable.contains(p)) {
+                self.push(LevelDiagnosticKind::UnreachableGold, to_row(*treasure), to_column(*treasure));
+            }
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Walk {
+    Open,
+    Blocker,
+    Ladder,
+    Rope,
+}
+
+// walks the level the same way the guards' navmesh does, plus the holes the runner can dig on
+// the way. this is only an approximation (holes refill, and you can't always get back out), which
+// is why unreachable gold is only a warning
+fn reachable_from(level: &LevelDataAsset, start: IVec2) -> HashSet<IVec2> {
+    use Walk::*;
+
+    let tiles: HashMap<IVec2, TileType> = level.tiles.iter().map(|t| (t.position, t.behaviour)).collect();
+    let in_bounds = |pos: IVec2| pos.x >= 0 && pos.x < level.width && pos.y >= 0 && pos.y < level.height;
+    // same as the game, above the level is open and everything else outside it is solid
+    let walk = |pos: IVec2| {
+        if !in_bounds(pos) {
+            return if pos.y >= level.height { Open } else { Blocker };
+        }
+        match tiles.get(&pos) {
+            Some(TileType::Brick | TileType::SolidBrick) => Blocker,
+            Some(TileType::Ladder) => Ladder,
+            Some(TileType::Rope) => Rope,
+            _ => Open,
+        }
+    };
+    let is_brick = |pos: IVec2| tiles.get(&pos) == Some(&TileType::Brick);
+
+    let (above, below, left, right) = (IVec2::new(0, 1), IVec2::new(0, -1), IVec2::new(-1, 0), IVec2::new(1, 0));
+
+    let mut visited = HashSet::new();
+    let mut open = VecDeque::new();
+    visited.insert(start);
+    open.push_back(start);
+
+    while let Some(pos) = open.pop_front() {
+        let on = walk(pos);
+        let under = walk(pos + below);
+        let mut next = Vec::new();
+
+        match on {
+            Open if under == Blocker || under == Ladder => next.extend([below, left, right]),
+            Open => next.push(below),
+            Rope => next.extend([below, left, right]),
+            Ladder => next.extend([above, below, left, right]),
+            // inside of a dug hole, we can only drop out of the bottom or walk out of the sides
+            Blocker if is_brick(pos) && under != Blocker => next.push(below),
+            Blocker if is_brick(pos) => next.extend([left, right]),
+            Blocker => {}
+        }
+        next.retain(|offset| walk(pos + *offset) != Blocker);
+        let mut next: Vec<IVec2> = next.into_iter().map(|offset| pos + offset).collect();
+
+        let can_dig = on == Rope || under == Blocker || under == Ladder;
+        if can_dig {
+            for side in [pos + left, pos + right] {
+                // the side could also be a brick we dug out first, which is how you dig down through a floor
+                let side_is_open = walk(side) != Blocker || is_brick(side);
+                if side_is_open && is_brick(side + below) {
+                    next.push(side + below);
+                }
+            }
+        }
+
+        for p in next {
+            if in_bounds(p) && visited.insert(p) {
+                open.push_back(p);
+            }
+        }
+    }
+
+    visited
+}
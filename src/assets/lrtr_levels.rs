@@ -0,0 +1,180 @@
+use bevy::{
+    asset::{AssetLoader, LoadContext},
+    utils::BoxedFuture,
+};
+
+use super::level_pack::LevelPackHeader;
+use super::level_validation::LevelValidation;
+use super::LevelAsset::{set_level_pack, LevelDataAsset, TileType};
+
+/// Levels in the web format are always 28x16
+pub const LRTR_LEVEL_WIDTH: i32 = 28;
+pub const LRTR_LEVEL_HEIGHT: i32 = 16;
+
+/// Pulls the levels out of a web level pack.
+///
+/// Packs are javascript (or json) arrays with one entry per level, each level being its 448 tiles as
+/// string literals joined together with `+`, ie.
+///
+/// ```text
+/// var classicData = [
+/// // level 001
+/// "                  S         " +
+/// "    $             S         " +
+/// ...
+/// ];
+/// ```
+///
+/// Anything outside of string literals is ignored, apart from the commas between levels.
+pub fn parse_lrtr_levels(text: &str) -> Result<Vec<String>, anyhow::Error> {
+    let mut levels = Vec::new();
+    let mut level = String::new();
+    let mut chars = text.chars().peekable();
+
+    while let Some(ch) = chars.next() {
+        match ch {
+            '"' | '\'' => loop {
+                match chars.next() {
+                    Some(c) if c == ch => break,
+                    Some('\\') => match chars.next() {
+                        Some(escaped) => level.push(escaped),
+                        None => anyhow::bail!("unterminated string in level pack"),
+                    },
+                    Some(c) => level.push(c),
+                    None => anyhow::bail!("unterminated string in level pack"),
+                }
+            },
+            '/' if chars.peek() == Some(&'/') => {
+                for c in chars.by_ref() {
+                    if c == '\n' {
+                        break;
+                    }
+                }
+            }
+            '/' if chars.peek() == Some(&'*') => {
+                chars.next();
+                let mut last = ' ';
+                for c in chars.by_ref() {
+                    if last == '*' && c == '/' {
+                        break;
+                    }
+                    last = c;
+                }
+            }
+            ',' | ']' if !level.is_empty() => levels.push(std::mem::take(&mut level)),
+            _ => {}
+        }
+    }
+    if !level.is_empty() {
+        levels.push(level);
+    }
+
+    if levels.is_empty() {
+        anyhow::bail!("level pack doesn't contain any levels");
+    }
+    Ok(levels)
+}
+
+/// Converts a single level from the web format, along with anything validation had to say about it
+pub fn decode_lrtr_level(level: &str) -> (LevelDataAsset, LevelValidation) {
+    let glyphs: Vec<char> = level.chars().collect();
+    let rows: Vec<String> = glyphs.chunks(LRTR_LEVEL_WIDTH as usize).map(|row| row.iter().collect()).collect();
+
+    // the web format uses the same glyphs as our own levels
+    let mut validation = LevelValidation::default();
+    validation.check_rows(&rows, LRTR_LEVEL_WIDTH, LRTR_LEVEL_HEIGHT, TileType::from_glyph);
+
+    let level_data = LevelDataAsset::from_rows(&rows, LRTR_LEVEL_WIDTH, LRTR_LEVEL_HEIGHT, TileType::from_glyph);
+    validation.check_level(&level_data);
+    (level_data, validation)
+}
+
+pub fn decode_lrtr_levels(bytes: &[u8]) -> Result<Vec<(LevelDataAsset, LevelValidation)>, anyhow::Error> {
+    let levels = parse_lrtr_levels(std::str::from_utf8(bytes)?)?;
+    Ok(levels.iter().map(|level| decode_lrtr_level(level)).collect())
+}
+
+/// Loads a web level pack, each level is available as a labelled asset
+/// numbered from one, ie. `total_recall.lrtr#017`
+#[derive(Default)]
+pub struct LrtrLevelsAssetLoader;
+
+impl AssetLoader for LrtrLevelsAssetLoader {
+    fn load<'a>(&'a self, bytes: &'a [u8], load_context: &'a mut LoadContext) -> BoxedFuture<'a, Result<(), anyhow::Error>> {
+        Box::pin(async move { set_level_pack(load_context, LevelPackHeader::default(), decode_lrtr_levels(bytes)?) })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["lrtr"]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::assets::level_validation::{LevelDiagnostic, LevelDiagnosticKind};
+    use bevy::math::IVec2;
+
+    const PACK: &str = include_str!("../../tests/fixtures/web.lrtr");
+
+    fn tile_at(level: &LevelDataAsset, x: i32, y: i32) -> Option<TileType> {
+        level.tiles.iter().find(|t| t.position == IVec2::new(x, y)).map(|t| t.behaviour)
+    }
+
+    #[test]
+    fn splits_levels() {
+        let levels = parse_lrtr_levels(PACK).unwrap();
+        assert_eq!(levels.len(), 2);
+        for level in &levels {
+            assert_eq!(level.chars().count() as i32, LRTR_LEVEL_WIDTH * LRTR_LEVEL_HEIGHT);
+        }
+    }
+
+    #[test]
+    fn maps_glyphs() {
+        let levels = decode_lrtr_levels(PACK.as_bytes()).unwrap();
+        let (level, validation) = &levels[0];
+        assert!(validation.diagnostics.is_empty(), "{:?}", validation.diagnostics);
+
+        assert_eq!(level.width, LRTR_LEVEL_WIDTH);
+        assert_eq!(level.height, LRTR_LEVEL_HEIGHT);
+        assert_eq!(tile_at(level, 18, 15), Some(TileType::HiddenLadder));
+        assert_eq!(tile_at(level, 4, 14), Some(TileType::Gold));
+        assert_eq!(tile_at(level, 8, 12), Some(TileType::Rope));
+        assert_eq!(tile_at(level, 5, 9), Some(TileType::Guard));
+        assert_eq!(tile_at(level, 14, 1), Some(TileType::Player));
+        assert_eq!(tile_at(level, 16, 0), Some(TileType::FalseBrick));
+        assert_eq!(tile_at(level, 0, 0), Some(TileType::SolidBrick));
+    }
+
+    #[test]
+    fn reports_unknown_glyphs() {
+        let levels = decode_lrtr_levels(PACK.as_bytes()).unwrap();
+        let (level, validation) = &levels[1];
+
+        assert_eq!(
+            validation.diagnostics,
+            vec![LevelDiagnostic {
+                kind: LevelDiagnosticKind::UnknownGlyph { glyph: '*' },
+                row: Some(14),
+                column: Some(18),
+            }]
+        );
+        assert_eq!(tile_at(level, 18, 1), None);
+    }
+
+    #[test]
+    fn reports_short_levels() {
+        let (_, validation) = decode_lrtr_level(&"#".repeat(30));
+        assert!(validation
+            .diagnostics
+            .iter()
+            .any(|d| d.kind == LevelDiagnosticKind::RowCount { expected: 16, found: 2 }));
+    }
+
+    #[test]
+    fn rejects_unterminated_strings() {
+        assert!(parse_lrtr_levels("[\"    ").is_err());
+        assert!(parse_lrtr_levels("var empty = [];").is_err());
+    }
+}
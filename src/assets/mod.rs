@@ -3,9 +3,27 @@ pub mod AssetLoading;
 #[allow(non_snake_case)]
 pub mod LevelAsset;
 pub mod animations;
+pub mod animgraph_asset;
+pub mod apple2_levels;
+pub mod atlas_asset;
+#[cfg(feature = "embedded_assets")]
+pub mod embedded_asset_io;
+pub mod level_pack;
+pub mod level_validation;
+pub mod lrtr_levels;
+pub mod overlay_asset_io;
 pub mod playlist_asset;
+pub mod scoring_asset;
+#[cfg(test)]
+pub mod test_util;
+pub mod tileset_asset;
 
 pub use animations::{AnimAsset, AnimAssetPlugin};
+pub use animgraph_asset::{AnimGraphAsset, AnimGraphAssetPlugin};
+pub use atlas_asset::{AtlasAsset, AtlasAssetPlugin};
+pub use level_pack::LevelPackAsset;
 pub use playlist_asset::{PlaylistAsset, PlaylistAssetPlugin};
-pub use AssetLoading::{AssetsLoading, LoadingLabel, LoadingPlugin, Progress};
+pub use scoring_asset::{ScoringAsset, ScoringAssetPlugin};
+pub use tileset_asset::{TilesetAsset, TilesetAssetPlugin};
+pub use AssetLoading::{AssetsLoading, FailedAsset, LoadErrors, LoadingLabel, LoadingPlugin, Progress, RecordLoadErrors};
 pub use LevelAsset::{LevelDataAsset, LevelDataAssetPlugin};
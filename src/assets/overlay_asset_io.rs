@@ -0,0 +1,150 @@
+use bevy::{
+    asset::{AssetIo, AssetIoError, FileAssetIo},
+    prelude::*,
+    tasks::IoTaskPool,
+    utils::BoxedFuture,
+};
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+
+/// Reads assets from several sources in order, a file in a later one shadows the same path in the earlier ones.
+/// Lets mods and user levels sit next to the shipped assets without touching them
+pub struct OverlayAssetIo {
+    layers: Vec<Box<dyn AssetIo>>,
+}
+
+impl OverlayAssetIo {
+    pub fn new(layers: Vec<Box<dyn AssetIo>>) -> Self {
+        Self { layers }
+    }
+}
+
+impl AssetIo for OverlayAssetIo {
+    fn load_path<'a>(&'a self, path: &'a Path) -> BoxedFuture<'a, Result<Vec<u8>, AssetIoError>> {
+        Box::pin(async move {
+            for layer in self.layers.iter().rev() {
+                match layer.load_path(path).await {
+                    Err(AssetIoError::NotFound(_)) => continue,
+                    result => return result,
+                }
+            }
+            Err(AssetIoError::NotFound(path.to_owned()))
+        })
+    }
+
+    fn read_directory(&self, path: &Path) -> Result<Box<dyn Iterator<Item = PathBuf>>, AssetIoError> {
+        if !self.is_directory(path) {
+            return Err(AssetIoError::NotFound(path.to_owned()));
+        }
+
+        // a file that's in several layers is still only listed once
+        let mut merged = BTreeSet::new();
+        for layer in self.layers.iter().filter(|layer| layer.is_directory(path)) {
+            merged.extend(layer.read_directory(path)?);
+        }
+        Ok(Box::new(merged.into_iter()))
+    }
+
+    fn is_directory(&self, path: &Path) -> bool {
+        self.layers.iter().any(|layer| layer.is_directory(path))
+    }
+
+    fn watch_path_for_changes(&self, _path: &Path) -> Result<(), AssetIoError> {
+        Ok(())
+    }
+
+    fn watch_for_changes(&self) -> Result<(), AssetIoError> {
+        // bevy only reloads assets for its own FileAssetIo, and there's no way to ask it to reload a path
+        Err(AssetIoError::Io(std::io::Error::new(
+            std::io::ErrorKind::Unsupported,
+            "hot reloading doesn't work with more than one asset root",
+        )))
+    }
+}
+
+/// Sets up the asset server with the given roots, has to be added before the `AssetPlugin`.
+///
+/// With the `embedded_assets` feature the assets built into the executable go underneath all of them
+pub struct OverlayAssetIoPlugin {
+    pub roots: Vec<PathBuf>,
+}
+
+impl OverlayAssetIoPlugin {
+    /// Hot reloading only works when there's a single layer, which is then used directly
+    pub fn can_watch_for_changes(&self) -> bool {
+        self.roots.len() + cfg!(feature = "embedded_assets") as usize == 1
+    }
+}
+
+impl Plugin for OverlayAssetIoPlugin {
+    fn build(&self, app: &mut App) {
+        let task_pool = app
+            .world
+            .get_resource::<IoTaskPool>()
+            .expect("IoTaskPool resource not found")
+            .0
+            .clone();
+
+        let mut layers: Vec<Box<dyn AssetIo>> = Vec::new();
+        #[cfg(feature = "embedded_assets")]
+        layers.push(Box::new(super::embedded_asset_io::EmbeddedAssetIo::new()));
+        for root in &self.roots {
+            layers.push(Box::new(FileAssetIo::new(root)));
+        }
+
+        // a single root keeps hot reloading working
+        let asset_io = if layers.len() == 1 {
+            layers.pop().unwrap()
+        } else {
+            Box::new(OverlayAssetIo::new(layers))
+        };
+        app.insert_resource(AssetServer::with_boxed_io(asset_io, task_pool));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::tasks::TaskPool;
+
+    #[test]
+    fn later_roots_shadow_earlier_ones() {
+        let dir = std::env::temp_dir().join(format!("loderunner-overlay-{}", std::process::id()));
+        let write = |path: &str| {
+            let path = dir.join(path);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(&path, path.to_string_lossy().as_bytes()).unwrap();
+        };
+        write("base/levels/001.level");
+        write("base/levels/002.level");
+        write("mod/levels/002.level");
+        write("mod/levels/extra.level");
+
+        let roots = [dir.join("base"), dir.join("mod")];
+        let overlay = OverlayAssetIo::new(
+            roots
+                .iter()
+                .map(|root| Box::new(FileAssetIo::new(root)) as Box<dyn AssetIo>)
+                .collect(),
+        );
+        let load = |path: &'static str| {
+            let overlay = &overlay;
+            TaskPool::new()
+                .scope(|scope| scope.spawn(async move { overlay.load_path(Path::new(path)).await.ok() }))
+                .remove(0)
+                .map(|bytes| PathBuf::from(String::from_utf8(bytes).unwrap()))
+        };
+        assert_eq!(load("levels/001.level"), Some(dir.join("base/levels/001.level")));
+        assert_eq!(load("levels/002.level"), Some(dir.join("mod/levels/002.level")));
+        assert_eq!(load("levels/003.level"), None);
+
+        let levels: Vec<PathBuf> = overlay.read_directory(Path::new("levels")).unwrap().collect();
+        assert_eq!(
+            levels,
+            ["levels/001.level", "levels/002.level", "levels/extra.level"].map(PathBuf::from)
+        );
+        assert!(overlay.read_directory(Path::new("tilesets")).is_err());
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
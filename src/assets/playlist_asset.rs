@@ -2,14 +2,350 @@ use bevy::{
     asset::{AssetLoader, LoadContext, LoadedAsset},
     prelude::*,
     reflect::TypeUuid,
-    utils::BoxedFuture,
+    utils::{BoxedFuture, HashSet},
 };
+use rand::{rngs::StdRng, seq::SliceRandom, SeedableRng};
 use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::ops::Range;
+use std::path::Path;
 
-#[derive(Debug, TypeUuid, Deserialize)]
+use super::level_pack::count_pack_levels;
+use super::AssetLoading::RecordLoadErrors;
+use super::LevelAsset::level_label;
+
+#[derive(Debug, TypeUuid)]
 #[uuid = "d973d6ed-c61e-4de1-bacc-63960676240e"]
 pub struct PlaylistAsset {
+    pub title: Option<String>,
+    /// Overrides the theme of every level in the playlist
+    pub theme: Option<String>,
+    /// Every entry of every section, in playing order
+    pub entries: Vec<PlaylistEntry>,
+    pub sections: Vec<PlaylistSection>,
+    pub end: PlaylistEnd,
+    pub order: PlaylistOrder,
+    /// Seed for shuffling and picking, a random one gets used when there isn't one
+    pub seed: Option<u64>,
+    /// Random levels played after the listed ones
+    pub pick: Option<PlaylistPick>,
+    pub lives: LivesConfig,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum PlaylistOrder {
+    InOrder,
+    /// Shuffles the levels of each section, sections stay in order
+    Shuffled,
+}
+
+impl Default for PlaylistOrder {
+    fn default() -> Self {
+        PlaylistOrder::InOrder
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct PlaylistPick {
+    /// Folder of `.level` files or a level pack
+    pub from: String,
+    pub count: usize,
+    #[serde(default)]
+    pub modifiers: LevelModifiers,
+    /// How many levels the pack to pick from has, counted when loading the playlist so the pack
+    /// itself only gets loaded a level at a time
+    #[serde(skip)]
+    pub pack_len: Option<usize>,
+}
+
+impl PlaylistPick {
+    /// Every level the pick can choose from, `folder` lists the levels in a folder
+    pub fn pool(&self, folder: impl Fn(&str) -> Vec<String>) -> Vec<String> {
+        match self.pack_len {
+            Some(len) => (0..len).map(|index| format!("{}#{}", self.from, level_label(index))).collect(),
+            None => folder(&self.from),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlaylistEntry {
+    pub level: String,
+    pub unlock: UnlockRequirements,
+    pub modifiers: LevelModifiers,
+}
+
+/// Changes to how a level plays, so the same levels can be remixed into new campaigns
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct LevelModifiers {
+    /// Flips the level left to right
+    pub mirror: bool,
+    /// Scales how fast guards run and climb
+    pub guard_speed: f32,
+    /// Seconds the runner has to finish the level in
+    pub time_limit: Option<f32>,
+    /// Guards to add on top of the level's own, they start where guards respawn
+    pub extra_guards: u32,
+    pub no_digging: bool,
+}
+
+impl LevelModifiers {
+    /// `what` names whatever the modifiers apply to in the error
+    fn validate(&self, what: &str) -> Result<(), anyhow::Error> {
+        if self.guard_speed <= 0.0 {
+            anyhow::bail!("{} has a guard_speed of {}, it needs to be positive", what, self.guard_speed);
+        }
+        if let Some(time_limit) = self.time_limit.filter(|time_limit| *time_limit <= 0.0) {
+            anyhow::bail!("{} has a time_limit of {}, it needs to be positive", what, time_limit);
+        }
+        Ok(())
+    }
+}
+
+impl Default for LevelModifiers {
+    fn default() -> Self {
+        Self {
+            mirror: false,
+            guard_speed: 1.0,
+            time_limit: None,
+            extra_guards: 0,
+            no_digging: false,
+        }
+    }
+}
+
+/// A named run of entries, ie. a world
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlaylistSection {
+    pub name: String,
+    /// Indices into `entries`
+    pub entries: Range<usize>,
+}
+
+/// What happens once the last level of a playlist has been completed
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum PlaylistEnd {
+    /// Start over from the first unlocked level
+    Loop,
+    /// The campaign is complete
+    Finish,
+    /// Carry on with the first unlocked level of another playlist
+    Chain(String),
+}
+
+impl Default for PlaylistEnd {
+    fn default() -> Self {
+        PlaylistEnd::Loop
+    }
+}
+
+/// How many tries the runner gets
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct LivesConfig {
+    pub start: u32,
+    /// Extra lives for completing a level
+    pub per_level: u32,
+    pub game_over: GameOverAction,
+}
+
+impl Default for LivesConfig {
+    fn default() -> Self {
+        Self {
+            start: 5,
+            per_level: 1,
+            game_over: GameOverAction::Restart,
+        }
+    }
+}
+
+/// What happens once the runner is out of lives
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum GameOverAction {
+    /// Start the campaign over from its first level
+    Restart,
+    /// Carry on from the level the lives ran out on
+    Continue,
+}
+
+/// Entries that are still locked get skipped
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct UnlockRequirements {
+    /// Levels that need to have been completed first
     pub levels: Vec<String>,
+    /// How many levels need to have been completed first
+    pub min_completed: usize,
+}
+
+impl UnlockRequirements {
+    pub fn is_met(&self, completed: &HashSet<String>) -> bool {
+        completed.len() >= self.min_completed && self.levels.iter().all(|level| completed.contains(level))
+    }
+}
+
+/// First entry at or after `from` that the completed levels unlock
+pub fn next_unlocked(entries: &[PlaylistEntry], from: usize, completed: &HashSet<String>) -> Option<usize> {
+    (from..entries.len()).find(|index| entries[*index].unlock.is_met(completed))
+}
+
+impl PlaylistAsset {
+    /// Entries in the order they get played, `pool` being every level `pick` can choose from.
+    ///
+    /// The same seed always gives the same order, shuffled entries stay inside of their section
+    pub fn resolve(&self, seed: u64, pool: &[String]) -> Vec<PlaylistEntry> {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let mut entries = self.entries.clone();
+
+        if self.order == PlaylistOrder::Shuffled {
+            let unsectioned = 0..self.sections.first().map_or(entries.len(), |section| section.entries.start);
+            for range in std::iter::once(unsectioned).chain(self.sections.iter().map(|section| section.entries.clone())) {
+                entries[range].shuffle(&mut rng);
+            }
+        }
+
+        if let Some(pick) = &self.pick {
+            let mut picked = pool.to_vec();
+            picked.shuffle(&mut rng);
+            entries.extend(picked.into_iter().take(pick.count).map(|level| PlaylistEntry {
+                level,
+                unlock: UnlockRequirements::default(),
+                modifiers: pick.modifiers.clone(),
+            }));
+        }
+
+        entries
+    }
+
+    pub fn section_of(&self, index: usize) -> Option<&PlaylistSection> {
+        self.sections.iter().find(|section| section.entries.contains(&index))
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PlaylistDiskAsset {
+    #[serde(default)]
+    pub title: Option<String>,
+    #[serde(default)]
+    pub theme: Option<String>,
+    /// Entries that aren't in any section
+    #[serde(default)]
+    pub levels: Vec<PlaylistEntryDiskAsset>,
+    #[serde(default)]
+    pub sections: Vec<PlaylistSectionDiskAsset>,
+    #[serde(default)]
+    pub end: PlaylistEnd,
+    #[serde(default)]
+    pub order: PlaylistOrder,
+    #[serde(default)]
+    pub seed: Option<u64>,
+    #[serde(default)]
+    pub pick: Option<PlaylistPick>,
+    #[serde(default)]
+    pub lives: LivesConfig,
+}
+
+/// Either a level path, or a level path with everything else an entry can have
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+pub enum PlaylistEntryDiskAsset {
+    Level(String),
+    Entry {
+        level: String,
+        #[serde(default)]
+        unlock: UnlockRequirements,
+        #[serde(default)]
+        modifiers: LevelModifiers,
+    },
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PlaylistSectionDiskAsset {
+    pub name: String,
+    pub levels: Vec<PlaylistEntryDiskAsset>,
+}
+
+impl From<PlaylistEntryDiskAsset> for PlaylistEntry {
+    fn from(entry: PlaylistEntryDiskAsset) -> Self {
+        match entry {
+            PlaylistEntryDiskAsset::Level(level) => PlaylistEntry {
+                level,
+                unlock: UnlockRequirements::default(),
+                modifiers: LevelModifiers::default(),
+            },
+            PlaylistEntryDiskAsset::Entry { level, unlock, modifiers } => PlaylistEntry { level, unlock, modifiers },
+        }
+    }
+}
+
+/// Flattens the playlist's sections into a single list of entries
+pub fn parse_playlist(bytes: &[u8]) -> Result<PlaylistAsset, anyhow::Error> {
+    let loaded_data = serde_json::de::from_slice::<PlaylistDiskAsset>(bytes)?;
+
+    // levels outside of a section play before any of the sections
+    let mut entries: Vec<PlaylistEntry> = loaded_data.levels.into_iter().map(PlaylistEntry::from).collect();
+    let mut sections = Vec::new();
+    for section in loaded_data.sections {
+        if section.levels.is_empty() {
+            anyhow::bail!("section {:?} has no levels", section.name);
+        }
+
+        let start = entries.len();
+        entries.extend(section.levels.into_iter().map(PlaylistEntry::from));
+        sections.push(PlaylistSection {
+            name: section.name,
+            entries: start..entries.len(),
+        });
+    }
+
+    match &loaded_data.pick {
+        Some(pick) if pick.count == 0 => anyhow::bail!("playlist picks no levels from {}", pick.from),
+        None if entries.is_empty() => anyhow::bail!("playlist has no levels"),
+        _ => {}
+    }
+    if loaded_data.lives.start == 0 {
+        anyhow::bail!("playlist starts with no lives");
+    }
+
+    for entry in &entries {
+        entry.modifiers.validate(&entry.level)?;
+    }
+    if let Some(pick) = &loaded_data.pick {
+        pick.modifiers.validate(&format!("the pick from {}", pick.from))?;
+    }
+
+    Ok(PlaylistAsset {
+        title: loaded_data.title,
+        theme: loaded_data.theme,
+        entries,
+        sections,
+        end: loaded_data.end,
+        order: loaded_data.order,
+        seed: loaded_data.seed,
+        pick: loaded_data.pick,
+        lives: loaded_data.lives,
+    })
+}
+
+/// Every `.level` file in a folder a playlist picks from
+pub fn levels_in(from: &str, asset_server: &AssetServer) -> Vec<String> {
+    let mut levels: Vec<String> = match asset_server.asset_io().read_directory(Path::new(from)) {
+        Ok(paths) => paths
+            .filter(|path| path.extension().and_then(|ext| ext.to_str()) == Some("level"))
+            .map(|path| path.to_string_lossy().replace('\\', "/"))
+            .collect(),
+        Err(err) => {
+            println!("WARNING:  can't pick levels from {}: {:?}", from, err);
+            Vec::new()
+        }
+    };
+    // directories get listed in whatever order the OS likes, which would change what a seed picks
+    levels.sort();
+    levels
 }
 
 #[derive(Default)]
@@ -20,7 +356,42 @@ impl AssetLoader for PlaylistAssetLoader {
         Box::pin(async move {
             // random sleep for testing the loading screen
             //std::thread::sleep(std::time::Duration::from_millis((1+(rand::random::<u64>() % 24)) * 1000));
-            let loaded_data = serde_json::de::from_slice::<PlaylistAsset>(bytes)?;
+            let mut loaded_data = parse_playlist(bytes)?;
+
+            // levels only get loaded once they're played, but a missing one should still show up before that.
+            // levels from the same pack only need reading once
+            let mut referenced: BTreeMap<String, Vec<String>> = BTreeMap::new();
+            for level in loaded_data.entries.iter().map(|entry| &entry.level) {
+                let labels = referenced.entry(level.split('#').next().unwrap().to_owned()).or_default();
+                labels.extend(level.split_once('#').map(|(_, label)| label.to_owned()));
+            }
+            if let PlaylistEnd::Chain(chained) = &loaded_data.end {
+                referenced.entry(chained.clone()).or_default();
+            }
+            for (path, labels) in &referenced {
+                let bytes = load_context
+                    .read_asset_bytes(path)
+                    .await
+                    .map_err(|err| anyhow::anyhow!("failed to read {}: {}", path, err))?;
+
+                // a label past the end of its pack would leave the level loading forever
+                if !labels.is_empty() {
+                    let pack_labels: Vec<String> = (0..count_pack_levels(Path::new(path), &bytes)?).map(level_label).collect();
+                    if let Some(label) = labels.iter().find(|label| !pack_labels.contains(label)) {
+                        anyhow::bail!("{} has no level #{}", path, label);
+                    }
+                }
+            }
+
+            // folders get listed when the playlist starts instead
+            if let Some(pick) = loaded_data.pick.as_mut().filter(|pick| Path::new(&pick.from).extension().is_some()) {
+                let pack_bytes = load_context
+                    .read_asset_bytes(&pick.from)
+                    .await
+                    .map_err(|err| anyhow::anyhow!("failed to read {}: {}", pick.from, err))?;
+                pick.pack_len = Some(count_pack_levels(Path::new(&pick.from), &pack_bytes)?);
+            }
+
             load_context.set_default_asset(LoadedAsset::new(loaded_data));
             Ok(())
         })
@@ -36,6 +407,136 @@ pub struct PlaylistAssetPlugin;
 impl Plugin for PlaylistAssetPlugin {
     fn build(&self, app: &mut App) {
         app.add_asset::<PlaylistAsset>();
-        app.init_asset_loader::<PlaylistAssetLoader>();
+        app.init_recording_asset_loader::<PlaylistAssetLoader>();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::assets::test_util::read_asset_bytes;
+
+    const WORLDS: &str = r#"{
+        "title": "Worlds",
+        "levels": ["levels/intro.level"],
+        "sections": [
+            { "name": "World 1", "levels": ["levels/1-1.level", "levels/1-2.level"] },
+            { "name": "World 2", "levels": [
+                "levels/2-1.level",
+                { "level": "levels/bonus.level", "unlock": { "levels": ["levels/secret.level"] } },
+                { "level": "levels/2-2.level", "unlock": { "min_completed": 4 }, "modifiers": { "mirror": true, "guard_speed": 1.5 } }
+            ] }
+        ],
+        "end": { "chain": "playlists/next.playlist" }
+    }"#;
+
+    #[test]
+    fn flattens_sections() {
+        let playlist = parse_playlist(WORLDS.as_bytes()).unwrap();
+        assert_eq!(playlist.title.as_deref(), Some("Worlds"));
+        assert_eq!(playlist.entries.len(), 6);
+        assert_eq!(playlist.entries[3].level, "levels/2-1.level");
+        assert_eq!(playlist.section_of(0), None);
+        assert_eq!(playlist.section_of(2).unwrap().name, "World 1");
+        assert_eq!(playlist.section_of(5).unwrap().entries, 3..6);
+        assert_eq!(playlist.end, PlaylistEnd::Chain("playlists/next.playlist".to_owned()));
+    }
+
+    #[test]
+    fn entries_carry_modifiers() {
+        let playlist = parse_playlist(WORLDS.as_bytes()).unwrap();
+        assert_eq!(playlist.entries[0].modifiers, LevelModifiers::default());
+
+        let modifiers = &playlist.entries[5].modifiers;
+        assert!(modifiers.mirror);
+        assert_eq!(modifiers.guard_speed, 1.5);
+        assert_eq!(modifiers.time_limit, None);
+    }
+
+    #[test]
+    fn rejects_bad_modifiers() {
+        let entry = |modifiers: &str| format!(r#"{{ "levels": [{{ "level": "levels/1-1.level", "modifiers": {} }}] }}"#, modifiers);
+        let pick = |modifiers: &str| {
+            format!(
+                r#"{{ "pick": {{ "from": "levels/classic", "count": 3, "modifiers": {} }} }}"#,
+                modifiers
+            )
+        };
+        for playlist in [entry, pick] {
+            assert!(parse_playlist(playlist(r#"{ "guard_speed": 2.0, "time_limit": 60 }"#).as_bytes()).is_ok());
+            assert!(parse_playlist(playlist(r#"{ "guard_speed": 0 }"#).as_bytes()).is_err());
+            assert!(parse_playlist(playlist(r#"{ "guard_speed": -1.5 }"#).as_bytes()).is_err());
+            assert!(parse_playlist(playlist(r#"{ "time_limit": 0 }"#).as_bytes()).is_err());
+        }
+    }
+
+    #[test]
+    fn skips_locked_entries() {
+        let playlist = parse_playlist(WORLDS.as_bytes()).unwrap();
+        let mut completed: HashSet<String> = ["intro", "1-1", "1-2"]
+            .iter()
+            .map(|name| format!("levels/{}.level", name))
+            .collect();
+        assert_eq!(next_unlocked(&playlist.entries, 4, &completed), None);
+
+        completed.insert("levels/2-1.level".to_owned());
+        assert_eq!(next_unlocked(&playlist.entries, 4, &completed), Some(5));
+
+        completed.insert("levels/secret.level".to_owned());
+        assert_eq!(next_unlocked(&playlist.entries, 4, &completed), Some(4));
+    }
+
+    #[test]
+    fn shuffles_inside_of_sections() {
+        let mut playlist = parse_playlist(WORLDS.as_bytes()).unwrap();
+        playlist.order = PlaylistOrder::Shuffled;
+
+        let levels = |seed| -> Vec<String> { playlist.resolve(seed, &[]).into_iter().map(|entry| entry.level).collect() };
+        let shuffled = levels(7);
+        assert_eq!(shuffled, levels(7));
+        assert_eq!(shuffled[0], "levels/intro.level");
+
+        let mut world_1 = shuffled[1..3].to_vec();
+        world_1.sort();
+        assert_eq!(world_1, ["levels/1-1.level", "levels/1-2.level"]);
+
+        // some seed has to change the order
+        assert!((0..32u64).map(levels).any(|order| order != shuffled));
+    }
+
+    #[test]
+    fn picks_from_the_pool() {
+        let playlist = parse_playlist(br#"{ "pick": { "from": "levels/classic", "count": 3 }, "seed": 42 }"#).unwrap();
+        let pool: Vec<String> = (1..=11).map(|index| format!("levels/classic/{:03}.level", index)).collect();
+
+        let entries = playlist.resolve(playlist.seed.unwrap(), &pool);
+        assert_eq!(entries.len(), 3);
+        assert!(entries.iter().all(|entry| pool.contains(&entry.level)));
+        assert_eq!(entries, playlist.resolve(42, &pool));
+
+        // the pool can be smaller than the count
+        assert_eq!(playlist.resolve(42, &pool[..2]).len(), 2);
+        assert!(parse_playlist(br#"{ "pick": { "from": "levels/classic", "count": 0 } }"#).is_err());
+    }
+
+    #[test]
+    fn lives_default_to_restarting() {
+        let playlist = parse_playlist(br#"{ "levels": ["levels/1-1.level"] }"#).unwrap();
+        assert_eq!(playlist.lives, LivesConfig::default());
+
+        let playlist = parse_playlist(br#"{ "levels": ["levels/1-1.level"], "lives": { "start": 3, "game_over": "continue" } }"#).unwrap();
+        assert_eq!(playlist.lives.start, 3);
+        assert_eq!(playlist.lives.per_level, 1);
+        assert_eq!(playlist.lives.game_over, GameOverAction::Continue);
+
+        assert!(parse_playlist(br#"{ "levels": ["levels/1-1.level"], "lives": { "start": 0 } }"#).is_err());
+    }
+
+    #[test]
+    fn shipped_playlists_parse() {
+        for name in ["classic.playlist", "debug.playlist"] {
+            let playlist = parse_playlist(&read_asset_bytes(format!("playlists/{}", name))).unwrap();
+            assert!(playlist.title.is_some(), "{}", name);
+        }
     }
 }
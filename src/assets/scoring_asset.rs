@@ -0,0 +1,67 @@
+use bevy::{
+    asset::{AssetLoader, BoxedFuture, LoadContext, LoadedAsset},
+    prelude::*,
+    reflect::TypeUuid,
+};
+use serde::Deserialize;
+
+use super::AssetLoading::RecordLoadErrors;
+
+/// Points for everything the runner can score, so they can be tuned without code changes
+#[derive(Debug, Clone, PartialEq, TypeUuid, Deserialize)]
+#[uuid = "e02fcd67-122b-4966-9767-187b10e3a4df"]
+pub struct ScoringAsset {
+    pub gold: u32,
+    /// A guard falling into a dug hole
+    pub guard_trapped: u32,
+    /// A guard still in a hole when the brick comes back
+    pub guard_killed: u32,
+    pub level_completed: u32,
+    /// For every second left on a level's time limit, or under its par time when it has no limit
+    pub per_second_left: u32,
+}
+
+impl ScoringAsset {
+    pub fn time_bonus(&self, seconds_left: f32) -> u32 {
+        seconds_left.max(0.0) as u32 * self.per_second_left
+    }
+}
+
+#[derive(Default)]
+pub struct ScoringAssetLoader;
+
+impl AssetLoader for ScoringAssetLoader {
+    fn load<'a>(&'a self, bytes: &'a [u8], load_context: &'a mut LoadContext) -> BoxedFuture<'a, Result<(), anyhow::Error>> {
+        Box::pin(async move {
+            let loaded_data = serde_json::de::from_slice::<ScoringAsset>(bytes)?;
+            load_context.set_default_asset(LoadedAsset::new(loaded_data));
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["scoring"]
+    }
+}
+
+pub struct ScoringAssetPlugin;
+
+impl Plugin for ScoringAssetPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_asset::<ScoringAsset>();
+        app.init_recording_asset_loader::<ScoringAssetLoader>();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::assets::test_util::read_asset;
+
+    #[test]
+    fn shipped_points_parse() {
+        let scoring: ScoringAsset = read_asset("points.scoring");
+        assert_eq!(scoring.time_bonus(12.7), 12 * scoring.per_second_left);
+        assert_eq!(scoring.time_bonus(-1.0), 0);
+    }
+}
@@ -0,0 +1,31 @@
+//! Reading the shipped assets from tests
+use serde::de::DeserializeOwned;
+use std::path::{Path, PathBuf};
+
+/// Where a path relative to `assets/` is on disk
+pub fn asset_path(path: impl AsRef<Path>) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("assets").join(path)
+}
+
+pub fn read_asset_bytes(path: impl AsRef<Path>) -> Vec<u8> {
+    let path = asset_path(path);
+    std::fs::read(&path).unwrap_or_else(|err| panic!("failed to read {}: {}", path.display(), err))
+}
+
+/// Parses an asset that's read straight from json
+pub fn read_asset<T: DeserializeOwned>(path: impl AsRef<Path>) -> T {
+    let path = path.as_ref();
+    serde_json::de::from_slice(&read_asset_bytes(path)).unwrap_or_else(|err| panic!("failed to parse {}: {}", path.display(), err))
+}
+
+/// Paths of the files in a folder with the given extension, relative to `assets/` and sorted
+pub fn asset_files(dir: impl AsRef<Path>, extension: &str) -> Vec<PathBuf> {
+    let dir = dir.as_ref();
+    let mut files: Vec<PathBuf> = std::fs::read_dir(asset_path(dir))
+        .unwrap()
+        .map(|entry| dir.join(entry.unwrap().file_name()))
+        .filter(|path| path.extension() == Some(extension.as_ref()))
+        .collect();
+    files.sort();
+    files
+}
@@ -0,0 +1,204 @@
+use bevy::{
+    asset::{AssetLoader, AssetPath, LoadContext, LoadedAsset},
+    prelude::*,
+    reflect::TypeUuid,
+    utils::BoxedFuture,
+};
+use serde::Deserialize;
+use std::collections::HashMap;
+
+use super::AssetLoading::RecordLoadErrors;
+use super::AssetLoading::{AssetsLoading, LoadErrors, Progress};
+use super::{atlas_asset::AtlasDiskAsset, AnimAsset, AtlasAsset, LevelAsset::TileType};
+
+/// Theme used when neither the playlist nor the level picks one
+pub const DEFAULT_THEME: &str = "classic";
+
+/// Every tile in a level with `"theme": "ice"` comes from `tilesets/ice.tileset`
+pub fn theme_path(theme: &str) -> String {
+    format!("tilesets/{}.tileset", theme)
+}
+
+/// How each kind of tile looks, so levels can be themed without code changes
+#[derive(Debug, TypeUuid)]
+#[uuid = "3e9f4a2b-8c1d-4f6e-a7b5-2d0c9e8f1a3b"]
+pub struct TilesetAsset {
+    pub tiles: HashMap<TileType, TileStyle>,
+}
+
+#[derive(Debug)]
+pub struct TileStyle {
+    pub atlas: Handle<AtlasAsset>,
+    pub frame: String,
+    pub color: Color,
+    pub anim: Option<Handle<AnimAsset>>,
+}
+
+impl TilesetAsset {
+    /// Looks up a tile's sprite, the atlas needs to have been loaded already.
+    ///
+    /// Styles and frames are checked when loading, a missing atlas still gives an empty sprite instead
+    pub fn sprite(&self, tile_type: TileType, atlases: &Assets<AtlasAsset>) -> TileSprite {
+        let (style, atlas) = match self
+            .tiles
+            .get(&tile_type)
+            .and_then(|style| Some((style, atlases.get(&style.atlas)?)))
+        {
+            Some(found) => found,
+            None => return TileSprite::default(),
+        };
+        let mut sprite = atlas.sprite(&style.frame).unwrap_or_default();
+        sprite.color = style.color;
+
+        TileSprite {
+            texture_atlas: atlas.texture_atlas.clone(),
+            sprite,
+            anim: style.anim.clone().unwrap_or_default(),
+        }
+    }
+}
+
+/// Everything a bundle needs to draw a tile
+#[derive(Clone, Default)]
+pub struct TileSprite {
+    pub texture_atlas: Handle<TextureAtlas>,
+    pub sprite: TextureAtlasSprite,
+    pub anim: Handle<AnimAsset>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TilesetDiskAsset {
+    pub tiles: HashMap<TileType, TileStyleDiskAsset>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TileStyleDiskAsset {
+    /// Path of an `.atlas`, from the assets directory
+    pub atlas: String,
+    pub frame: String,
+    /// Tint as a hex string, ie. `"a0d8ff"`, white when omitted
+    #[serde(default)]
+    pub color: Option<String>,
+    /// Path of an `.anim`, from the assets directory
+    #[serde(default)]
+    pub anim: Option<String>,
+}
+
+// these get animated, so there's no way to draw them without an anim
+const ANIMATED_TILES: [TileType; 3] = [TileType::Brick, TileType::Guard, TileType::Player];
+
+#[derive(Default)]
+pub struct TilesetAssetLoader;
+
+impl AssetLoader for TilesetAssetLoader {
+    fn load<'a>(&'a self, bytes: &'a [u8], load_context: &'a mut LoadContext) -> BoxedFuture<'a, Result<(), anyhow::Error>> {
+        Box::pin(async move {
+            let loaded_data = serde_json::de::from_slice::<TilesetDiskAsset>(bytes)?;
+
+            for tile_type in TileType::ALL {
+                match loaded_data.tiles.get(&tile_type) {
+                    None => anyhow::bail!("tileset has no style for {:?}", tile_type),
+                    Some(style) if style.anim.is_none() && ANIMATED_TILES.contains(&tile_type) => {
+                        anyhow::bail!("{:?} needs an anim", tile_type)
+                    }
+                    _ => {}
+                }
+            }
+
+            // the frames are only looked up by name when drawing, so make sure they're all there
+            let mut atlases: HashMap<&str, AtlasDiskAsset> = HashMap::new();
+            for (tile_type, style) in &loaded_data.tiles {
+                if !atlases.contains_key(style.atlas.as_str()) {
+                    let atlas_bytes = load_context
+                        .read_asset_bytes(&style.atlas)
+                        .await
+                        .map_err(|err| anyhow::anyhow!("failed to read atlas {}: {}", style.atlas, err))?;
+                    atlases.insert(&style.atlas, serde_json::de::from_slice::<AtlasDiskAsset>(&atlas_bytes)?);
+                }
+                if !atlases[style.atlas.as_str()].frames.contains_key(&style.frame) {
+                    anyhow::bail!(
+                        "{:?} uses frame {:?}, but {} has no frame by that name",
+                        tile_type,
+                        style.frame,
+                        style.atlas
+                    );
+                }
+            }
+
+            let mut dependencies = Vec::new();
+            let mut tiles = HashMap::new();
+            for (tile_type, style) in loaded_data.tiles {
+                let color = match &style.color {
+                    Some(hex) => Color::hex(hex).map_err(|err| anyhow::anyhow!("{:?} has a bad color {:?}: {:?}", tile_type, hex, err))?,
+                    None => Color::WHITE,
+                };
+
+                let atlas_path = AssetPath::from(style.atlas.as_str()).to_owned();
+                let anim_path = style.anim.as_deref().map(|anim| AssetPath::from(anim).to_owned());
+                let tile_style = TileStyle {
+                    atlas: load_context.get_handle(atlas_path.clone()),
+                    frame: style.frame,
+                    color,
+                    anim: anim_path.clone().map(|anim_path| load_context.get_handle(anim_path)),
+                };
+
+                dependencies.push(atlas_path);
+                dependencies.extend(anim_path);
+                tiles.insert(tile_type, tile_style);
+            }
+
+            load_context.set_default_asset(LoadedAsset::new(TilesetAsset { tiles }).with_dependencies(dependencies));
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["tileset"]
+    }
+}
+
+/// Tracks the atlases and anims behind every loaded tileset, which finish loading after the tileset does
+pub fn tileset_dependencies_progress(
+    server: Res<AssetServer>,
+    errors: Res<LoadErrors>,
+    mut loading: ResMut<AssetsLoading>,
+    tilesets: Res<Assets<TilesetAsset>>,
+    atlases: Res<Assets<AtlasAsset>>,
+    animations: Res<Assets<AnimAsset>>,
+) -> Progress {
+    use bevy::asset::LoadState;
+
+    let mut progress = Progress::default();
+    for (_, tileset) in tilesets.iter() {
+        for style in tileset.tiles.values() {
+            // a failed atlas is done, loading then ends in the error state
+            progress.total += 1;
+            if atlases.contains(&style.atlas) {
+                progress.done += 1;
+            } else if server.get_load_state(&style.atlas) == LoadState::Failed {
+                loading.fail_load(&style.atlas, &server, &errors);
+                progress.done += 1;
+            }
+
+            if let Some(anim) = &style.anim {
+                progress.total += 1;
+                if animations.contains(anim) {
+                    progress.done += 1;
+                } else if server.get_load_state(anim) == LoadState::Failed {
+                    loading.fail_load(anim, &server, &errors);
+                    progress.done += 1;
+                }
+            }
+        }
+    }
+    progress
+}
+
+pub struct TilesetAssetPlugin;
+
+impl Plugin for TilesetAssetPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_asset::<TilesetAsset>();
+        app.init_recording_asset_loader::<TilesetAssetLoader>();
+    }
+}
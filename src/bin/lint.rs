@@ -0,0 +1,199 @@
+//! Checks every `.level`, level pack (`.lrpack`, `.a2levels`, `.lrtr`) and `.playlist` under an assets directory
+//!
+//! usage: lint [assets directory]
+//!
+//! Exits with a non-zero status if any errors were found.
+use loderunner::assets::{
+    apple2_levels::decode_apple2_levels,
+    level_pack::parse_level_pack,
+    level_validation::{LevelValidation, Severity},
+    lrtr_levels::decode_lrtr_levels,
+    playlist_asset::{parse_playlist, PlaylistEnd},
+    LevelAsset::{level_label, parse_level, LevelDataAsset},
+};
+use std::path::{Path, PathBuf};
+
+#[derive(Default)]
+struct LintReport {
+    errors: u32,
+    warnings: u32,
+}
+
+impl LintReport {
+    fn error(&mut self, message: String) {
+        println!("{}", message);
+        self.errors += 1;
+    }
+
+    fn warning(&mut self, message: String) {
+        println!("{}", message);
+        self.warnings += 1;
+    }
+}
+
+fn main() {
+    let assets_dir = std::env::args()
+        .nth(1)
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from("assets"));
+
+    let mut files = Vec::new();
+    if let Err(err) = collect_files(&assets_dir, &mut files) {
+        eprintln!("failed to read {}: {}", assets_dir.display(), err);
+        std::process::exit(2);
+    }
+    files.sort();
+
+    let mut report = LintReport::default();
+    for file in &files {
+        match file.extension().and_then(|ext| ext.to_str()) {
+            Some("level") => lint_level(file, &mut report),
+            Some("playlist") => lint_playlist(&assets_dir, file, &mut report),
+            _ => {
+                if let Some(decode) = pack_decoder(file) {
+                    lint_level_pack(file, decode, &mut report);
+                }
+            }
+        }
+    }
+
+    println!("{} error(s), {} warning(s)", report.errors, report.warnings);
+    if report.errors > 0 {
+        std::process::exit(1);
+    }
+}
+
+fn collect_files(dir: &Path, files: &mut Vec<PathBuf>) -> std::io::Result<()> {
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            collect_files(&path, files)?;
+        } else {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+fn lint_level(path: &Path, report: &mut LintReport) {
+    let parsed = std::fs::read(path)
+        .map_err(anyhow::Error::from)
+        .and_then(|bytes| parse_level(&bytes));
+    match parsed {
+        Ok((_, validation)) => report_validation(path.display(), &validation, report),
+        Err(err) => report.error(format!("{}: error: {}", path.display(), err)),
+    }
+}
+
+type DecodeLevels = fn(&[u8]) -> Result<Vec<(LevelDataAsset, LevelValidation)>, anyhow::Error>;
+
+// files holding several levels, which get loaded as `file#001`, `file#002`...
+fn pack_decoder(path: &Path) -> Option<DecodeLevels> {
+    match path.extension().and_then(|ext| ext.to_str()) {
+        Some("lrpack") => Some(|bytes| Ok(parse_level_pack(bytes)?.1)),
+        Some("a2levels") => Some(decode_apple2_levels),
+        Some("lrtr") => Some(decode_lrtr_levels),
+        _ => None,
+    }
+}
+
+fn lint_level_pack(path: &Path, decode: DecodeLevels, report: &mut LintReport) {
+    let decoded = std::fs::read(path).map_err(anyhow::Error::from).and_then(|bytes| decode(&bytes));
+    match decoded {
+        Ok(levels) => {
+            for (index, (_, validation)) in levels.iter().enumerate() {
+                report_validation(format!("{}#{}", path.display(), level_label(index)), validation, report);
+            }
+        }
+        Err(err) => report.error(format!("{}: error: {}", path.display(), err)),
+    }
+}
+
+fn report_validation(path: impl std::fmt::Display, validation: &LevelValidation, report: &mut LintReport) {
+    for diagnostic in &validation.diagnostics {
+        let message = diagnostic.report(&path);
+        match diagnostic.severity() {
+            Severity::Error => report.error(message),
+            Severity::Warning => report.warning(message),
+        }
+    }
+}
+
+fn lint_playlist(assets_dir: &Path, path: &Path, report: &mut LintReport) {
+    let parsed = std::fs::read(path)
+        .map_err(anyhow::Error::from)
+        .and_then(|bytes| parse_playlist(&bytes));
+    let playlist = match parsed {
+        Ok(playlist) => playlist,
+        Err(err) => {
+            report.error(format!("{}: error: {}", path.display(), err));
+            return;
+        }
+    };
+
+    for (index, entry) in playlist.entries.iter().enumerate() {
+        if !level_exists(assets_dir, &entry.level) {
+            report.error(format!(
+                "{}:{}: error: level {} doesn't exist",
+                path.display(),
+                index + 1,
+                entry.level
+            ));
+        }
+        // levels from a playlist chaining to this one count too, so this is only a warning
+        for required in entry
+            .unlock
+            .levels
+            .iter()
+            .filter(|required| !playlist.entries.iter().any(|e| e.level == **required))
+        {
+            report.warning(format!(
+                "{}:{}: warning: {} needs {} to unlock, which isn't in the playlist",
+                path.display(),
+                index + 1,
+                entry.level,
+                required
+            ));
+        }
+    }
+
+    if let PlaylistEnd::Chain(chained) = &playlist.end {
+        if !assets_dir.join(chained).is_file() {
+            report.error(format!(
+                "{}: error: chains to playlist {}, which doesn't exist",
+                path.display(),
+                chained
+            ));
+        }
+    }
+
+    if let Some(pick) = &playlist.pick {
+        if !assets_dir.join(&pick.from).exists() {
+            report.error(format!(
+                "{}: error: picks levels from {}, which doesn't exist",
+                path.display(),
+                pick.from
+            ));
+        }
+    }
+}
+
+fn level_exists(assets_dir: &Path, level: &str) -> bool {
+    // labelled assets live inside of the file before the '#'
+    let (level_file, label) = match level.split_once('#') {
+        Some((level_file, label)) => (assets_dir.join(level_file), Some(label)),
+        None => (assets_dir.join(level), None),
+    };
+    if !level_file.is_file() {
+        return false;
+    }
+
+    match (label, pack_decoder(&level_file)) {
+        (None, _) => true,
+        (Some(label), Some(decode)) => std::fs::read(&level_file)
+            .map_err(anyhow::Error::from)
+            .and_then(|bytes| decode(&bytes))
+            .map_or(false, |levels| (0..levels.len()).any(|index| level_label(index) == label)),
+        (Some(_), None) => false,
+    }
+}
@@ -0,0 +1,69 @@
+//! Bundles `.level` files into a single `.lrpack`
+//!
+//! usage: pack [-name NAME] [-author AUTHOR] <output.lrpack> <level file or directory>...
+//!
+//! Directories are expanded to the `.level` files directly inside of them, sorted by file name,
+//! and levels end up in the pack in the order they were given.
+use loderunner::assets::{
+    level_pack::{write_level_pack, LevelPackHeader},
+    LevelAsset::parse_level,
+};
+use std::path::PathBuf;
+
+fn main() {
+    if let Err(err) = run() {
+        eprintln!("error: {}", err);
+        std::process::exit(1);
+    }
+}
+
+fn run() -> Result<(), anyhow::Error> {
+    let mut header = LevelPackHeader::default();
+    let mut paths = Vec::new();
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-name" => header.name = args.next(),
+            "-author" => header.author = args.next(),
+            _ => paths.push(PathBuf::from(arg)),
+        }
+    }
+
+    if paths.len() < 2 {
+        anyhow::bail!("usage: pack [-name NAME] [-author AUTHOR] <output.lrpack> <level file or directory>...");
+    }
+    let output = paths.remove(0);
+
+    let mut levels = Vec::new();
+    for path in paths {
+        let mut files = if path.is_dir() {
+            let mut files = Vec::new();
+            for entry in std::fs::read_dir(&path)? {
+                let file = entry?.path();
+                if file.extension().and_then(|ext| ext.to_str()) == Some("level") {
+                    files.push(file);
+                }
+            }
+            files
+        } else {
+            vec![path]
+        };
+        files.sort();
+
+        for file in files {
+            let (level, validation) = parse_level(&std::fs::read(&file)?).map_err(|err| anyhow::anyhow!("{}: {}", file.display(), err))?;
+            for diagnostic in &validation.diagnostics {
+                println!("{}", diagnostic.report(file.display()));
+            }
+            if validation.has_errors() {
+                anyhow::bail!("{} has errors", file.display());
+            }
+            levels.push(level);
+        }
+    }
+
+    std::fs::write(&output, write_level_pack(&header, &levels)?)?;
+    println!("wrote {} level(s) to {}", levels.len(), output.display());
+    Ok(())
+}
@@ -1,6 +1,4 @@
-use crate::{
-    game::resources::LevelResource, CoreAssets, MAP_SIZE_HALF_WIDTH, MAP_SIZE_HEIGHT, MAP_SIZE_WIDTH, TILE_SIZE_HEIGHT, TILE_SIZE_WIDTH,
-};
+use crate::{assets::AtlasAsset, game::resources::LevelResource, TILE_SIZE_HEIGHT, TILE_SIZE_WIDTH};
 use bevy::prelude::*;
 
 use super::{
@@ -91,9 +89,10 @@ fn find_nearest_runner<'a>(to_position: IVec2, runners: &'a Query<&GridTransform
 }
 
 #[allow(dead_code)]
-pub fn debug_navmesh(commands: &mut Commands, core_assets: &Res<CoreAssets>, navmesh: &NavMesh) {
+pub fn debug_navmesh(commands: &mut Commands, debug_atlas: &AtlasAsset, navmesh: &NavMesh, level: &LevelResource) {
+    let level_offset = level.world_offset() + Vec3::new(0.0, 0.0, 0.1);
     for tile in &navmesh.tiles {
-        commands.spawn_bundle(DebugNavTile::new(&core_assets.debug_atlas, tile, navmesh));
+        commands.spawn_bundle(DebugNavTile::new(debug_atlas, tile, navmesh, level_offset));
     }
 }
 
@@ -129,8 +128,8 @@ impl NavMesh {
 
         // build raw tiles
         let mut raw_nav_tiles: Vec<RawNavTile> = Vec::new();
-        for y in 0..MAP_SIZE_HEIGHT {
-            for x in 0..MAP_SIZE_WIDTH {
+        for y in 0..level.height() {
+            for x in 0..level.width() {
                 let pos = IVec2::new(x, y);
                 let tiles = level.around(pos);
 
@@ -298,13 +297,7 @@ pub struct DebugNavTile {
 }
 
 impl DebugNavTile {
-    pub fn new(texture: &Handle<TextureAtlas>, tile: &NavTile, navmesh: &NavMesh) -> Self {
-        let level_offset = Vec3::new(
-            MAP_SIZE_HALF_WIDTH as f32 * TILE_SIZE_WIDTH * -1.0 + (TILE_SIZE_WIDTH / 2.0),
-            TILE_SIZE_HEIGHT / 2.0,
-            0.1,
-        );
-
+    pub fn new(atlas: &AtlasAsset, tile: &NavTile, navmesh: &NavMesh, level_offset: Vec3) -> Self {
         let pos = Vec3::new(tile.pos.x as f32 * TILE_SIZE_WIDTH, tile.pos.y as f32 * TILE_SIZE_HEIGHT, 0.0) + level_offset;
 
         let mut top = false;
@@ -336,7 +329,7 @@ impl DebugNavTile {
 
         Self {
             sprite: TextureAtlasSprite::new(index),
-            texture_atlas: texture.clone(),
+            texture_atlas: atlas.texture_atlas.clone(),
             transform: Transform::from_scale(Vec3::splat(1.0)).with_translation(pos),
             ..Default::default()
         }
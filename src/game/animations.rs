@@ -1,128 +1,192 @@
+pub use crate::assets::animations::{ANIMATION_EVENTS, DESPAWN_EVENT, DIG_FINISHED_EVENT};
+use crate::assets::animgraph_asset::{AnimCondition, AnimGraphAsset};
 use crate::assets::AnimAsset;
 use bevy::prelude::*;
 
 use super::{components::*, movement::Movement, resources::LevelResource};
 
-pub fn animgraph_runner(
-    level: Res<LevelResource>,
-    mut runners: Query<(&Movement, &GridTransform, &Runner, &mut SpriteAnimator), With<Runner>>,
-) {
-    for (movement, transform, runner, mut animator) in runners.iter_mut() {
-        // initialized?
-        if animator.animation_name == Option::None {
-            animator.animation_name = Some("runRight".to_string());
-        }
-
-        // animations only active when we're moving, otherwise we stay at whatever frame we were at
-        animator.active = movement.velocity != Vec3::ZERO || runner.is_burning();
-        if animator.active {
-            let tiles = level.around(transform.translation);
-
-            if runner.is_burning() {
-                if runner.burning_left {
-                    animator.switch("digLeft")
-                } else {
-                    animator.switch("digRight")
-                }
-            } else {
-                animgraph_character_movement(movement, animator, tiles);
-            }
-        }
-    }
+// everything about an entity an animgraph can look at
+struct AnimSubject<'a> {
+    level: &'a LevelResource,
+    movement: Option<&'a Movement>,
+    transform: Option<&'a GridTransform>,
+    runner: Option<&'a Runner>,
+    stunned: bool,
+    respawn: Option<&'a Respawnable>,
+    burnable: Option<&'a Burnable>,
 }
 
-pub fn animgraph_brick(mut query: Query<(&Burnable, &mut SpriteAnimator)>) {
-    use BurnState::*;
-    for (burnable, mut animator) in query.iter_mut() {
-        animator.active = true;
-        match burnable.get_state() {
-            Burning => animator.switch("burning"),
-            Rebuilding => animator.switch("rebuilding"),
-            NotBurning => animator.switch("default"),
-            _ => {}
+impl AnimSubject<'_> {
+    fn holds(&self, condition: AnimCondition) -> bool {
+        use crate::game::resources::EffectiveTileType::Rope;
+        use AnimCondition::*;
+
+        let velocity = self.movement.map_or(Vec3::ZERO, |movement| movement.velocity);
+        let burn_state = self.burnable.map(|burnable| burnable.get_state());
+        match condition {
+            Moving => velocity != Vec3::ZERO,
+            MovingLeft => velocity.x < 0.0,
+            MovingRight => velocity.x > 0.0,
+            MovingVertically => velocity.y != 0.0,
+            Falling => matches!(self.movement, Some(movement) if movement.is_falling()),
+            FallingLeft => matches!(self.movement, Some(movement) if movement.get_fall_direction() < 0.0),
+            OnRope => matches!(self.transform, Some(transform) if self.level.around(transform.translation).on.behaviour == Rope),
+            Burning => matches!(self.runner, Some(runner) if runner.is_burning()),
+            BurningLeft => matches!(self.runner, Some(runner) if runner.burning_left),
+            Stunned => self.stunned,
+            WaitingToRespawn => matches!(self.respawn, Some(respawn) if respawn.timer > 0.0),
+            Respawning => matches!(self.respawn, Some(respawn) if respawn.timer > 2.0),
+            BrickBurning => burn_state == Some(BurnState::Burning),
+            BrickRebuilding => burn_state == Some(BurnState::Rebuilding),
+            BrickNotBurning => burn_state == Some(BurnState::NotBurning),
         }
     }
 }
 
+/// Switches sequences for everything whose anim has an animgraph
 #[allow(clippy::type_complexity)]
-pub fn animgraph_guard(
+pub fn run_animgraphs(
     level: Res<LevelResource>,
-    mut guards: Query<(&Movement, &GridTransform, Option<&Stunned>, &mut SpriteAnimator, &Respawnable), With<Guard>>,
+    animations: Res<Assets<AnimAsset>>,
+    graphs: Res<Assets<AnimGraphAsset>>,
+    mut animated: Query<(
+        &Handle<AnimAsset>,
+        &mut SpriteAnimator,
+        Option<&Movement>,
+        Option<&GridTransform>,
+        Option<&Runner>,
+        Option<&Stunned>,
+        Option<&Respawnable>,
+        Option<&Burnable>,
+    )>,
 ) {
-    for (movement, transform, stunned, mut animator, respawn) in guards.iter_mut() {
+    for (anim, mut animator, movement, transform, runner, stunned, respawn, burnable) in animated.iter_mut() {
+        let graph = match animations
+            .get(anim)
+            .and_then(|anim| anim.graph.as_ref())
+            .and_then(|graph| graphs.get(graph))
+        {
+            Some(graph) => graph,
+            None => continue,
+        };
+
         // initialized?
-        if animator.animation_name == Option::None {
-            animator.animation_name = Some("runRight".to_string());
+        if animator.animation_name.is_none() {
+            animator.animation_name = graph.initial.clone();
         }
 
-        // animations only active when we're moving, otherwise we stay at whatever frame we were at
-        let is_stunned = stunned.is_some();
-        animator.active = movement.velocity != Vec3::ZERO || is_stunned || respawn.timer > 0.0;
+        let subject = AnimSubject {
+            level: &level,
+            movement,
+            transform,
+            runner,
+            stunned: stunned.is_some(),
+            respawn,
+            burnable,
+        };
+
+        // when inactive we stay at whatever frame we were at
+        animator.active = graph.is_active(|condition| subject.holds(condition));
         if animator.active {
-            let tiles = level.around(transform.translation);
-            if is_stunned {
-                if movement.get_fall_direction() >= 0.0 {
-                    animator.switch("stunnedRight")
-                } else {
-                    animator.switch("stunnedLeft")
-                }
-            } else if respawn.timer > 2.0 {
-                animator.switch("respawn")
-            } else {
-                animgraph_character_movement(movement, animator, tiles);
+            if let Some(sequence) = graph.sequence(|condition| subject.holds(condition)) {
+                animator.switch(sequence);
             }
         }
     }
 }
 
-fn animgraph_character_movement(movement: &Movement, mut animator: Mut<SpriteAnimator>, tiles: super::resources::TilesAround) {
-    use crate::game::resources::EffectiveTileType::*;
-    if movement.is_falling() {
-        if movement.get_fall_direction() >= 0.0 {
-            animator.switch("fallRight")
-        } else {
-            animator.switch("fallLeft")
-        }
-    } else if tiles.on.behaviour == Rope && movement.velocity.x > 0.0 {
-        animator.switch("ropeRight")
-    } else if tiles.on.behaviour == Rope && movement.velocity.x < 0.0 {
-        animator.switch("ropeLeft")
-    } else if movement.velocity.x > 0.0 {
-        animator.switch("runRight")
-    } else if movement.velocity.x < 0.0 {
-        animator.switch("runLeft")
-    } else if movement.velocity.y != 0.0 {
-        animator.switch("runUpDown")
-    }
+/// Sent by `animate_sprites` for the events in an anim's sequences
+#[derive(Debug, Clone)]
+pub struct AnimationEvent {
+    pub entity: Entity,
+    pub name: String,
 }
 
+#[allow(clippy::type_complexity)]
 pub fn animate_sprites(
     time: Res<Time>,
     animations: Res<Assets<AnimAsset>>,
-    mut animated_sprites: Query<(&mut TextureAtlasSprite, &mut SpriteAnimator, &Handle<AnimAsset>)>,
+    mut events: EventWriter<AnimationEvent>,
+    mut animated_sprites: Query<(
+        Entity,
+        &mut TextureAtlasSprite,
+        &mut Visibility,
+        &mut SpriteAnimator,
+        &Handle<AnimAsset>,
+    )>,
 ) {
-    for (mut sprite, mut anim, anim_handle) in animated_sprites.iter_mut() {
-        let anim_data = animations.get(anim_handle).unwrap();
-        let frame_time = 1.0 / anim_data.fps;
+    for (entity, mut sprite, mut visibility, mut anim, anim_handle) in animated_sprites.iter_mut() {
+        // an anim that failed to load ends up in LoadingFailed, there is nothing to play
+        let anim_data = match animations.get(anim_handle) {
+            Some(anim_data) => anim_data,
+            None => continue,
+        };
 
         if let Some(animation_name) = &anim.animation_name {
-            let anim_sequence = &anim_data.sequence[animation_name];
+            // the anim could have been edited since this animation started
+            let anim_sequence = match anim_data.sequence.get(animation_name) {
+                Some(anim_sequence) if !anim_sequence.frames.is_empty() => anim_sequence,
+                _ => continue,
+            };
 
             // always set the current frame, just in case we switched animations
-            sprite.index = anim_sequence.frames[anim.frame_index];
+            sprite.index = anim_sequence.frames[anim.frame_index.min(anim_sequence.frames.len() - 1)];
+            sprite.flip_x = anim_sequence.flip_x;
 
             // if we're not active, don't run the current anim
             if !anim.active {
                 continue;
             }
 
+            let mut send = |name: &str| {
+                events.send(AnimationEvent {
+                    entity,
+                    name: name.to_owned(),
+                })
+            };
+
+            if !anim.started {
+                anim.started = true;
+                if anim.hidden {
+                    anim.hidden = false;
+                    visibility.is_visible = true;
+                }
+                anim_sequence.events_on(anim.frame_index).for_each(&mut send);
+            }
+
             // run frames until we've caught up
             anim.elapsed += time.delta_seconds();
-            while anim.elapsed > frame_time {
-                anim.frame_index = anim_sequence.next_frame(anim.frame_index);
+            while !anim.finished {
+                let frame_time = anim_sequence.frame_time(anim.frame_index, anim_data.fps);
+                if anim.elapsed <= frame_time {
+                    break;
+                }
                 anim.elapsed -= frame_time;
-                sprite.index = anim_sequence.frames[anim.frame_index];
+
+                match anim_sequence.next_frame(anim.frame_index, anim.reversed) {
+                    Some((frame_index, reversed)) => {
+                        anim.frame_index = frame_index;
+                        anim.reversed = reversed;
+                        sprite.index = anim_sequence.frames[frame_index];
+                        anim_sequence.events_on(frame_index).for_each(&mut send);
+                    }
+                    None => {
+                        anim.finished = true;
+                        if anim_sequence.hide_on_finished {
+                            anim.hidden = true;
+                            visibility.is_visible = false;
+                        }
+                        anim_sequence.on_finished.iter().for_each(|name| send(name));
+                    }
+                }
             }
         }
     }
 }
+
+/// Removes anything whose animation sends a `despawn` event, ie. effects that only play once
+pub fn despawn_on_animation_event(mut commands: Commands, mut events: EventReader<AnimationEvent>) {
+    for event in events.iter().filter(|e| e.name == DESPAWN_EVENT) {
+        commands.entity(event.entity).despawn_recursive();
+    }
+}
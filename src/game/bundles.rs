@@ -1,4 +1,8 @@
-use crate::{assets::AnimAsset, game::components::*, TILE_SIZE_HEIGHT, TILE_SIZE_WIDTH};
+use crate::{
+    assets::{tileset_asset::TileSprite, AnimAsset, AtlasAsset},
+    game::components::*,
+    TILE_SIZE_HEIGHT, TILE_SIZE_WIDTH,
+};
 use bevy::prelude::*;
 
 use super::{ai::AiController, movement::Movement};
@@ -19,16 +23,16 @@ pub struct BrickBundle {
 }
 
 impl BrickBundle {
-    pub fn new(texture: &Handle<TextureAtlas>, anim: &Handle<AnimAsset>, position: Vec3, offset: Vec3) -> Self {
+    pub fn new(tile: &TileSprite, position: Vec3, offset: Vec3) -> Self {
         Self {
-            sprite: TextureAtlasSprite::new(35),
-            texture_atlas: texture.clone(),
+            sprite: tile.sprite.clone(),
+            texture_atlas: tile.texture_atlas.clone(),
             transform: Transform::from_scale(Vec3::splat(1.0)).with_translation(position),
             grid_transform: GridTransform {
                 offset,
                 ..Default::default()
             },
-            anim_data: anim.clone(),
+            anim_data: tile.anim.clone(),
             ..Default::default()
         }
     }
@@ -44,10 +48,10 @@ pub struct FalseBrickBundle {
 }
 
 impl FalseBrickBundle {
-    pub fn new(texture: &Handle<TextureAtlas>, position: Vec3) -> Self {
+    pub fn new(tile: &TileSprite, position: Vec3) -> Self {
         Self {
-            sprite: TextureAtlasSprite::new(1), // 7 for editor
-            texture_atlas: texture.clone(),
+            sprite: tile.sprite.clone(),
+            texture_atlas: tile.texture_atlas.clone(),
             transform: Transform::from_scale(Vec3::splat(1.0)).with_translation(position),
             ..Default::default()
         }
@@ -66,10 +70,10 @@ pub struct GoldBundle {
 }
 
 impl GoldBundle {
-    pub fn new(texture: &Handle<TextureAtlas>, position: Vec3) -> Self {
+    pub fn new(tile: &TileSprite, position: Vec3) -> Self {
         Self {
-            sprite: TextureAtlasSprite::new(4),
-            texture_atlas: texture.clone(),
+            sprite: tile.sprite.clone(),
+            texture_atlas: tile.texture_atlas.clone(),
             transform: Transform::from_scale(Vec3::splat(1.0)).with_translation(position),
             overlap: Overlaps {
                 height: 11.0,
@@ -100,11 +104,11 @@ pub struct GuardBundle {
 }
 
 impl GuardBundle {
-    pub fn new(texture: &Handle<TextureAtlas>, anim: &Handle<AnimAsset>, position: Vec3, offset: Vec3) -> Self {
+    pub fn new(tile: &TileSprite, position: Vec3, offset: Vec3, speed_multiplier: f32) -> Self {
         Self {
-            sprite: TextureAtlasSprite::new(0),
-            anim_data: anim.clone(),
-            texture_atlas: texture.clone(),
+            sprite: tile.sprite.clone(),
+            anim_data: tile.anim.clone(),
+            texture_atlas: tile.texture_atlas.clone(),
             transform: Transform::from_scale(Vec3::splat(1.0)).with_translation(position),
             grid_transform: GridTransform {
                 offset,
@@ -119,7 +123,7 @@ impl GuardBundle {
                 max: 1,
                 ..Default::default()
             },
-            movement: Movement::new(TILE_SIZE_WIDTH * 4.0, TILE_SIZE_HEIGHT * 4.0),
+            movement: Movement::new(TILE_SIZE_WIDTH * 4.0 * speed_multiplier, TILE_SIZE_HEIGHT * 4.0 * speed_multiplier),
             ..Default::default()
         }
     }
@@ -137,10 +141,10 @@ pub struct HiddenLadderBundle {
 }
 
 impl HiddenLadderBundle {
-    pub fn new(texture: &Handle<TextureAtlas>, position: Vec3, offset: Vec3) -> Self {
+    pub fn new(tile: &TileSprite, position: Vec3, offset: Vec3) -> Self {
         Self {
-            sprite: TextureAtlasSprite::new(5), // 2 for editor
-            texture_atlas: texture.clone(),
+            sprite: tile.sprite.clone(),
+            texture_atlas: tile.texture_atlas.clone(),
             transform: Transform::from_scale(Vec3::splat(1.0)).with_translation(position),
             visibility: Visibility { is_visible: false },
             grid_transform: GridTransform {
@@ -162,10 +166,10 @@ pub struct LadderBundle {
 }
 
 impl LadderBundle {
-    pub fn new(texture: &Handle<TextureAtlas>, position: Vec3) -> Self {
+    pub fn new(tile: &TileSprite, position: Vec3) -> Self {
         Self {
-            sprite: TextureAtlasSprite::new(5),
-            texture_atlas: texture.clone(),
+            sprite: tile.sprite.clone(),
+            texture_atlas: tile.texture_atlas.clone(),
             transform: Transform::from_scale(Vec3::splat(1.0)).with_translation(position),
             ..Default::default()
         }
@@ -191,16 +195,16 @@ pub struct PlayerBundle {
 }
 
 impl PlayerBundle {
-    pub fn new(texture: &Handle<TextureAtlas>, anim: &Handle<AnimAsset>, position: Vec3, offset: Vec3) -> Self {
+    pub fn new(tile: &TileSprite, position: Vec3, offset: Vec3) -> Self {
         Self {
-            sprite: TextureAtlasSprite::new(0),
-            texture_atlas: texture.clone(),
+            sprite: tile.sprite.clone(),
+            texture_atlas: tile.texture_atlas.clone(),
             transform: Transform::from_scale(Vec3::splat(1.0)).with_translation(position),
             grid_transform: GridTransform {
                 offset,
                 ..Default::default()
             },
-            anim_data: anim.clone(),
+            anim_data: tile.anim.clone(),
             pickup: GoldPickup {
                 max: 99,
                 ..Default::default()
@@ -221,10 +225,10 @@ pub struct RopeBundle {
 }
 
 impl RopeBundle {
-    pub fn new(texture: &Handle<TextureAtlas>, position: Vec3) -> Self {
+    pub fn new(tile: &TileSprite, position: Vec3) -> Self {
         Self {
-            sprite: TextureAtlasSprite::new(6),
-            texture_atlas: texture.clone(),
+            sprite: tile.sprite.clone(),
+            texture_atlas: tile.texture_atlas.clone(),
             transform: Transform::from_scale(Vec3::splat(1.0)).with_translation(position),
             ..Default::default()
         }
@@ -241,10 +245,10 @@ pub struct SolidBrickBundle {
 }
 
 impl SolidBrickBundle {
-    pub fn new(texture: &Handle<TextureAtlas>, position: Vec3) -> Self {
+    pub fn new(tile: &TileSprite, position: Vec3) -> Self {
         Self {
-            sprite: TextureAtlasSprite::new(0),
-            texture_atlas: texture.clone(),
+            sprite: tile.sprite.clone(),
+            texture_atlas: tile.texture_atlas.clone(),
             transform: Transform::from_scale(Vec3::splat(1.0)).with_translation(position),
             ..Default::default()
         }
@@ -260,17 +264,15 @@ pub struct SpriteEffectBundle {
     pub visibility: Visibility,
     pub anim_data: Handle<AnimAsset>,
     pub sprite_anim: SpriteAnimator,
-    pub spawn_after: DespawnAfter,
 }
 
 impl SpriteEffectBundle {
-    pub fn new(texture: &Handle<TextureAtlas>, anim: &Handle<AnimAsset>, position: Vec3, starting_anim: &str) -> Self {
+    pub fn new(atlas: &AtlasAsset, anim: &Handle<AnimAsset>, position: Vec3, starting_anim: &str) -> Self {
         Self {
             sprite: TextureAtlasSprite::new(0),
-            texture_atlas: texture.clone(),
+            texture_atlas: atlas.texture_atlas.clone(),
             anim_data: anim.clone(),
             transform: Transform::from_scale(Vec3::splat(1.0)).with_translation(position),
-            spawn_after: DespawnAfter::new(0.5),
             sprite_anim: SpriteAnimator::new(starting_anim),
             ..Default::default()
         }
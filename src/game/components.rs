@@ -11,7 +11,6 @@ pub struct Runner {
 
     pub burning_left: bool,
     pub burning_right: bool,
-    pub burn_time: f32,
 }
 
 impl Runner {
@@ -143,6 +142,14 @@ pub struct SpriteAnimator {
     pub animation_name: Option<String>,
     pub elapsed: f32,
     pub active: bool,
+    /// Events for the first frame have been sent
+    pub started: bool,
+    /// A sequence that doesn't loop is over
+    pub finished: bool,
+    /// Going backwards through a ping-pong sequence
+    pub reversed: bool,
+    /// Hidden by a sequence that hides on finishing, shown again when the next one starts
+    pub hidden: bool,
 }
 
 impl SpriteAnimator {
@@ -152,17 +159,29 @@ impl SpriteAnimator {
             animation_name: Some(starting_anim.to_owned()),
             elapsed: 0.0,
             active: true,
+            started: false,
+            finished: false,
+            reversed: false,
+            hidden: false,
         }
     }
 
     pub fn switch(&mut self, anim: &str) {
         let new_anim = Some(anim.to_string());
         if new_anim != self.animation_name {
-            self.frame_index = 0;
             self.animation_name = new_anim;
-            self.elapsed = 0.0;
+            self.restart();
         }
     }
+
+    /// Starts the current animation over from its first frame
+    pub fn restart(&mut self) {
+        self.frame_index = 0;
+        self.elapsed = 0.0;
+        self.started = false;
+        self.finished = false;
+        self.reversed = false;
+    }
 }
 
 impl Default for SpriteAnimator {
@@ -172,6 +191,10 @@ impl Default for SpriteAnimator {
             animation_name: None,
             elapsed: 0.0,
             active: false,
+            started: false,
+            finished: false,
+            reversed: false,
+            hidden: false,
         }
     }
 }
@@ -188,22 +211,5 @@ pub struct Respawnable {
     pub position: IVec2,
 }
 
-#[derive(Component, Debug, Clone)]
-pub struct DespawnAfter {
-    pub time_remaining: f32,
-}
-
-impl DespawnAfter {
-    pub fn new(time: f32) -> Self {
-        Self { time_remaining: time }
-    }
-}
-
-impl Default for DespawnAfter {
-    fn default() -> Self {
-        DespawnAfter::new(1.0)
-    }
-}
-
 #[derive(Component, Debug, Clone, Default)]
 pub struct Victory;
@@ -1,73 +1,151 @@
-use crate::assets::playlist_asset::PlaylistAsset;
-use crate::assets::AnimAsset;
+use crate::assets::playlist_asset::{levels_in, LevelModifiers, PlaylistAsset};
+use crate::assets::tileset_asset::{theme_path, TileSprite, DEFAULT_THEME};
+use crate::assets::AssetLoading::{AssetsLoading, Progress};
 use crate::assets::LevelAsset::*;
 use crate::assets::LevelDataAsset;
+use crate::assets::{AtlasAsset, ScoringAsset, TilesetAsset};
 use crate::game::ai::NavMesh;
 use crate::game::PlaylistState;
 use crate::game::{bundles::*, components::*, resources::*};
 use crate::AppStates;
 use crate::CoreAssets;
-use crate::{MAP_SIZE_HALF_WIDTH, MAP_SIZE_HEIGHT, MAP_SIZE_WIDTH, TILE_SIZE_HEIGHT, TILE_SIZE_WIDTH};
+use crate::{TILE_SIZE_HEIGHT, TILE_SIZE_WIDTH};
+use bevy::asset::LoadState;
 use bevy::prelude::*;
 
+use super::animations::{AnimationEvent, DIG_FINISHED_EVENT};
 use super::movement::Falling;
 use super::movement::Movement;
 
 pub struct SpawnableResources {
     pub fire_left: SpriteEffectBundle,
     pub fire_right: SpriteEffectBundle,
+    pub gold: TileSprite,
 }
 
+/// Starts loading the level of the current playlist entry, which replaces the previous one
+pub fn load_level(
+    asset_server: Res<AssetServer>,
+    playlist_state: Res<PlaylistState>,
+    mut level_assets: ResMut<LevelAssets>,
+    mut loading: ResMut<AssetsLoading>,
+) {
+    // a level from a pack loads the whole pack, but only the level itself is kept around
+    let level_handle = asset_server.load(playlist_state.current_level());
+    loading.add(&level_handle);
+    *level_assets = LevelAssets {
+        level: level_handle,
+        tileset: None,
+    };
+}
+
+/// Loads the tileset of the level being loaded, which can't be picked before the level is there
+pub fn level_tileset_progress(
+    asset_server: Res<AssetServer>,
+    playlist_state: Res<PlaylistState>,
+    playlists: Res<Assets<PlaylistAsset>>,
+    level_datas: Res<Assets<LevelDataAsset>>,
+    tilesets: Res<Assets<TilesetAsset>>,
+    mut level_assets: ResMut<LevelAssets>,
+) -> Progress {
+    let level_data = match level_datas.get(&level_assets.level) {
+        Some(level_data) => level_data,
+        // a failed level gets reported by AssetsLoading
+        None if asset_server.get_load_state(&level_assets.level) == LoadState::Failed => return Progress { done: 1, total: 1 },
+        None => return Progress { done: 0, total: 1 },
+    };
+
+    let theme = level_theme(&playlist_state, &playlists, level_data);
+    let tileset = level_assets
+        .tileset
+        .get_or_insert_with(|| asset_server.load(theme_path(theme).as_str()));
+
+    // a missing theme falls back to the default tileset, which is always loaded
+    let done = tilesets.contains(&*tileset) || asset_server.get_load_state(&*tileset) == LoadState::Failed;
+    Progress {
+        done: done as u32,
+        total: 1,
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
 pub fn init_gameplay(
     mut commands: Commands,
     core_assets: Res<CoreAssets>,
     playlist_state: Res<PlaylistState>,
     level_datas: Res<Assets<LevelDataAsset>>,
     playlists: Res<Assets<PlaylistAsset>>,
-    animations: Res<Assets<AnimAsset>>,
+    atlases: Res<Assets<AtlasAsset>>,
+    tilesets: Res<Assets<TilesetAsset>>,
 ) {
-    let level_path = playlist_state.current_level(&playlists);
-    let level_data = level_datas.get(level_path).unwrap();
+    let entry = playlist_state.current_entry();
+    let level_data = level_datas.get(entry.level.as_str()).unwrap();
+    let mirrored_level_data;
+    let level_data = if entry.modifiers.mirror {
+        mirrored_level_data = level_data.mirrored();
+        &mirrored_level_data
+    } else {
+        level_data
+    };
+    let tileset = level_tileset(&playlist_state, &playlists, level_data, &tilesets);
     let mut level = LevelResource::from_asset(level_data);
-    spawn_level_entities(&mut commands, &core_assets, level_data, &animations, &mut level);
+    spawn_level_entities(&mut commands, tileset, &atlases, level_data, &entry.modifiers, &mut level);
     let level_navmesh = NavMesh::from_level(&level);
-    //debug_navmesh(&mut commands, &core_assets, &level_navmesh);
+    //debug_navmesh(&mut commands, atlases.get(&core_assets.debug_atlas).unwrap(), &level_navmesh, &level);
     commands.insert_resource(level_navmesh);
     commands.insert_resource(level);
-    commands.insert_resource(LevelState { ..Default::default() });
-
-    let fire_atlas = &core_assets.hole_atlas;
-    let fire_anim = &animations.get_handle("anims/fire.anim");
+    commands.insert_resource(LevelState {
+        time_left: entry.modifiers.time_limit,
+        par_seconds: level_data.header.par_seconds,
+        ..Default::default()
+    });
+    commands.insert_resource(entry.modifiers.clone());
+
+    let fire_atlas = atlases.get(&core_assets.hole_atlas).unwrap();
+    let fire_anim = &core_assets.fire_anim;
     let spawnables = SpawnableResources {
         fire_left: SpriteEffectBundle::new(fire_atlas, fire_anim, Vec3::ZERO, "left"),
         fire_right: SpriteEffectBundle::new(fire_atlas, fire_anim, Vec3::ZERO, "right"),
+        gold: tileset.sprite(TileType::Gold, &atlases),
     };
     commands.insert_resource(spawnables);
 }
 
+/// The playlist's theme wins over the level's own, and everything else gets the default
+fn level_theme<'a>(playlist_state: &PlaylistState, playlists: &'a Assets<PlaylistAsset>, level_data: &'a LevelDataAsset) -> &'a str {
+    let playlist = playlists.get(playlist_state.playlist_handle()).unwrap();
+    playlist
+        .theme
+        .as_deref()
+        .or(level_data.header.theme.as_deref())
+        .unwrap_or(DEFAULT_THEME)
+}
+
+fn level_tileset<'a>(
+    playlist_state: &PlaylistState,
+    playlists: &Res<Assets<PlaylistAsset>>,
+    level_data: &LevelDataAsset,
+    tilesets: &'a Res<Assets<TilesetAsset>>,
+) -> &'a TilesetAsset {
+    let theme = level_theme(playlist_state, playlists, level_data);
+    tilesets.get(theme_path(theme).as_str()).unwrap_or_else(|| {
+        println!("WARNING:  theme {:?} doesn't exist, using {:?}", theme, DEFAULT_THEME);
+        tilesets.get(theme_path(DEFAULT_THEME).as_str()).expect("default theme is missing")
+    })
+}
+
 #[derive(Component)]
 pub struct LevelSpecificComponent;
 
 fn spawn_level_entities(
     commands: &mut Commands,
-    core_assets: &Res<CoreAssets>,
+    tileset: &TilesetAsset,
+    atlases: &Res<Assets<AtlasAsset>>,
     level_data: &LevelDataAsset,
-    animations: &Res<Assets<AnimAsset>>,
+    modifiers: &LevelModifiers,
     level: &mut LevelResource,
 ) {
-    let tiles_atlas = &core_assets.tiles_atlas;
-    let guard_atlas = &core_assets.guard_atlas;
-    let guard_anim = &animations.get_handle("anims/guard.anim");
-    let hole_atlas = &core_assets.hole_atlas;
-    let hole_anim = &animations.get_handle("anims/brick.anim");
-    let runner_atlas = &core_assets.runner_atlas;
-    let runner_anim = &animations.get_handle("anims/runner.anim");
-
-    let level_offset = Vec3::new(
-        MAP_SIZE_HALF_WIDTH as f32 * TILE_SIZE_WIDTH * -1.0 + (TILE_SIZE_WIDTH / 2.0),
-        TILE_SIZE_HEIGHT / 2.0,
-        0.05,
-    );
+    let level_offset = level.world_offset() + Vec3::new(0.0, 0.0, 0.05);
 
     for tile in &level_data.tiles {
         let pos = Vec3::new(
@@ -76,24 +154,40 @@ fn spawn_level_entities(
             0.0,
         ) + level_offset;
 
+        let sprite = &tileset.sprite(tile.behaviour, atlases);
         let tile_id = match tile.behaviour {
-            TileType::Brick => commands.spawn_bundle(BrickBundle::new(hole_atlas, hole_anim, pos, level_offset)),
-            TileType::FalseBrick => commands.spawn_bundle(FalseBrickBundle::new(tiles_atlas, pos)),
-            TileType::Gold => commands.spawn_bundle(GoldBundle::new(tiles_atlas, pos)),
-            TileType::Guard => commands.spawn_bundle(GuardBundle::new(guard_atlas, guard_anim, pos, level_offset)),
-            TileType::HiddenLadder => commands.spawn_bundle(HiddenLadderBundle::new(tiles_atlas, pos, level_offset)),
-            TileType::Ladder => commands.spawn_bundle(LadderBundle::new(tiles_atlas, pos)),
-            TileType::Player => commands.spawn_bundle(PlayerBundle::new(runner_atlas, runner_anim, pos, level_offset)),
-            TileType::Rope => commands.spawn_bundle(RopeBundle::new(tiles_atlas, pos)),
-            TileType::SolidBrick => commands.spawn_bundle(SolidBrickBundle::new(tiles_atlas, pos)),
+            TileType::Brick => commands.spawn_bundle(BrickBundle::new(sprite, pos, level_offset)),
+            TileType::FalseBrick => commands.spawn_bundle(FalseBrickBundle::new(sprite, pos)),
+            TileType::Gold => commands.spawn_bundle(GoldBundle::new(sprite, pos)),
+            TileType::Guard => commands.spawn_bundle(GuardBundle::new(sprite, pos, level_offset, modifiers.guard_speed)),
+            TileType::HiddenLadder => commands.spawn_bundle(HiddenLadderBundle::new(sprite, pos, level_offset)),
+            TileType::Ladder => commands.spawn_bundle(LadderBundle::new(sprite, pos)),
+            TileType::Player => commands.spawn_bundle(PlayerBundle::new(sprite, pos, level_offset)),
+            TileType::Rope => commands.spawn_bundle(RopeBundle::new(sprite, pos)),
+            TileType::SolidBrick => commands.spawn_bundle(SolidBrickBundle::new(sprite, pos)),
         }
         .insert(LevelSpecificComponent)
         .id();
         level.set_entity(tile.position, tile_id);
     }
 
-    for x in 0..MAP_SIZE_WIDTH {
-        let pos = Vec3::new(x as f32 * TILE_SIZE_WIDTH, MAP_SIZE_HEIGHT as f32 * TILE_SIZE_HEIGHT, 0.0) + level_offset;
+    let guard_sprite = tileset.sprite(TileType::Guard, atlases);
+    for _ in 0..modifiers.extra_guards {
+        let position = match level.get_random_respawn() {
+            Some(position) => position,
+            None => {
+                println!("WARNING:  no room to spawn extra guards on this level");
+                break;
+            }
+        };
+        let pos = Vec3::new(position.x as f32 * TILE_SIZE_WIDTH, position.y as f32 * TILE_SIZE_HEIGHT, 0.0) + level_offset;
+        commands
+            .spawn_bundle(GuardBundle::new(&guard_sprite, pos, level_offset, modifiers.guard_speed))
+            .insert(LevelSpecificComponent);
+    }
+
+    for x in 0..level_data.width {
+        let pos = Vec3::new(x as f32 * TILE_SIZE_WIDTH, level_data.height as f32 * TILE_SIZE_HEIGHT, 0.0) + level_offset;
         commands.spawn_bundle(VictoryTileBundle::new(pos)).insert(LevelSpecificComponent);
     }
 }
@@ -128,15 +222,21 @@ pub fn player_input(keyboard_input: Res<Input<KeyCode>>, mut players: Query<(&mu
     }
 }
 
+#[allow(clippy::type_complexity)]
 pub fn start_burns(
-    time: Res<Time>,
     level: Res<LevelResource>,
+    modifiers: Res<LevelModifiers>,
     mut commands: Commands,
     spawnables: Res<SpawnableResources>,
-    mut runners: Query<(&GridTransform, &mut Runner, &mut Transform, &mut Movement), Without<Falling>>,
+    mut runners: Query<(&GridTransform, &mut Runner, &mut Transform, &mut Movement, &mut SpriteAnimator), Without<Falling>>,
     mut all_burnables: Query<&mut Burnable>,
 ) {
-    for (transform, mut runner, mut world_transform, mut movement) in runners.iter_mut() {
+    // without digging a burn never starts
+    if modifiers.no_digging {
+        return;
+    }
+
+    for (transform, mut runner, mut world_transform, mut movement, mut animator) in runners.iter_mut() {
         // check to see if we should start a burn
         if !runner.is_burning() {
             let tiles = level.around(transform.translation);
@@ -145,28 +245,30 @@ pub fn start_burns(
                 fire.transform.translation = transform.to_world(tiles.left.pos);
                 commands.spawn_bundle(fire).insert(LevelSpecificComponent);
                 runner.burning_left = true;
-                runner.burn_time = 0.0;
+                animator.restart();
                 world_transform.translation.x = transform.snap(world_transform.translation).x;
             } else if runner.wants_to_burn_right && start_burn(&tiles.below_right, &mut all_burnables) {
                 let mut fire = spawnables.fire_right.clone();
                 fire.transform.translation = transform.to_world(tiles.right.pos);
                 commands.spawn_bundle(fire).insert(LevelSpecificComponent);
                 runner.burning_right = true;
-                runner.burn_time = 0.0;
+                animator.restart();
                 world_transform.translation.x = transform.snap(world_transform.translation).x;
             }
         }
-        // we're in a burn already, so don't do anything except tick a timer
+        // we're in a burn already, so don't do anything until the dig animation says we're done
         else {
             // clear any movement
             movement.consume();
+        }
+    }
+}
 
-            runner.burn_time += time.delta_seconds();
-            if runner.burn_time >= (10.0 / 22.0) {
-                runner.burning_left = false;
-                runner.burning_right = false;
-                runner.burn_time = 0.0;
-            }
+pub fn finish_burns(mut events: EventReader<AnimationEvent>, mut runners: Query<&mut Runner>) {
+    for event in events.iter().filter(|e| e.name == DIG_FINISHED_EVENT) {
+        if let Ok(mut runner) = runners.get_mut(event.entity) {
+            runner.burning_left = false;
+            runner.burning_right = false;
         }
     }
 }
@@ -183,12 +285,17 @@ fn start_burn(tile: &Tile, all_burnables: &mut Query<&mut Burnable>) -> bool {
     false
 }
 
+#[allow(clippy::too_many_arguments)]
 pub fn apply_burnables(
     mut commands: Commands,
     time: Res<Time>,
+    scoring: Res<ScoringAsset>,
+    mut score: ResMut<Score>,
     mut level: ResMut<LevelResource>,
     mut query: Query<(&mut Burnable, &GridTransform, &Overlaps)>,
     killables: Query<&Killable>,
+    guards: Query<(), With<Guard>>,
+    trapped_guards: Query<(), (With<Guard>, Added<Stunned>)>,
 ) {
     use BurnState::*;
 
@@ -204,6 +311,12 @@ pub fn apply_burnables(
             }
             Burnt => {
                 level.set(transform.translation, EffectiveTileType::None);
+                let trapped = overlaps
+                    .entities
+                    .iter()
+                    .filter(|entity| trapped_guards.get(**entity).is_ok())
+                    .count();
+                score.points += trapped as u32 * scoring.guard_trapped;
                 if burnable.burn_time > 4.5 {
                     burnable.set_state(Rebuilding);
                 }
@@ -216,6 +329,9 @@ pub fn apply_burnables(
                     for overlapping_entity in &overlaps.entities {
                         if killables.get(*overlapping_entity).is_ok() {
                             commands.entity(*overlapping_entity).insert(Killed {});
+                            if guards.get(*overlapping_entity).is_ok() {
+                                score.points += scoring.guard_killed;
+                            }
                         }
                     }
                 }
@@ -225,14 +341,17 @@ pub fn apply_burnables(
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 pub fn gold_pickups(
     mut commands: Commands,
+    scoring: Res<ScoringAsset>,
+    mut score: ResMut<Score>,
     level: Res<LevelResource>,
     mut state: ResMut<LevelState>,
-    mut players: Query<(&mut GoldPickup, &Overlaps)>,
+    mut players: Query<(&mut GoldPickup, &Overlaps, Option<&Runner>)>,
     treasures: Query<Entity, With<Treasure>>,
 ) {
-    for (mut pickup, overlap) in players.iter_mut() {
+    for (mut pickup, overlap, runner) in players.iter_mut() {
         for entity in &overlap.entities {
             // make sure it's gold (overlap could be anything)
             if let Ok(gold_entity) = treasures.get(*entity) {
@@ -241,9 +360,12 @@ pub fn gold_pickups(
                     break;
                 }
 
-                // pick it up and destroy it
+                // pick it up and destroy it, guards carry gold around without scoring
                 pickup.count += 1;
                 commands.entity(gold_entity).despawn_recursive();
+                if runner.is_some() {
+                    score.points += scoring.gold;
+                }
             }
         }
 
@@ -269,9 +391,15 @@ pub fn show_exit_ladders(
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 pub fn next_level(
     mut app_state: ResMut<State<AppStates>>,
     mut playlist_state: ResMut<PlaylistState>,
+    mut lives: ResMut<Lives>,
+    mut score: ResMut<Score>,
+    state: Res<LevelState>,
+    scoring: Res<ScoringAsset>,
+    asset_server: Res<AssetServer>,
     playlists: Res<Assets<PlaylistAsset>>,
     players: Query<&Overlaps, With<Runner>>,
     victory_tiles: Query<Entity, With<Victory>>,
@@ -279,8 +407,15 @@ pub fn next_level(
     for player_overlap in players.iter() {
         for overlapping_entity in &player_overlap.entities {
             if victory_tiles.get(*overlapping_entity).is_ok() {
-                playlist_state.next_level(&playlists);
-                app_state.set(AppStates::ChangeLevel).expect("failed to change state");
+                lives.left += playlist_state.lives_config(&playlists).per_level;
+
+                score.points += scoring.level_completed + state.seconds_left().map_or(0, |seconds_left| scoring.time_bonus(seconds_left));
+                playlist_state.next_level(&playlists, |from| levels_in(from, &asset_server));
+                if playlist_state.is_campaign_complete() {
+                    app_state.set(AppStates::CampaignComplete).expect("failed to change state");
+                } else {
+                    app_state.set(AppStates::ChangeLevel).expect("failed to change state");
+                }
                 break;
             }
         }
@@ -305,7 +440,13 @@ pub fn start_guard_respawn(
 ) {
     for (entity, mut respawn, mut visibility, mut transform, mut overlaps, grid_transform) in new_dead_guards.iter_mut() {
         respawn.timer = 0.0;
-        respawn.position = level.get_random_respawn();
+        match level.get_random_respawn() {
+            Some(position) => respawn.position = position,
+            // nowhere to come back, the guard stays dead
+            None => {
+                commands.entity(entity).remove::<Respawnable>();
+            }
+        }
 
         visibility.is_visible = false;
         overlaps.is_active = false;
@@ -347,15 +488,14 @@ pub fn respawn_guard(
 
 pub fn guard_drop_treasure(
     mut commands: Commands,
-    core_assets: Res<CoreAssets>,
+    spawnables: Res<SpawnableResources>,
     mut query: Query<(&GridTransform, &mut GoldPickup), Added<Stunned>>,
 ) {
     for (grid_transform, mut gold_pickup) in query.iter_mut() {
         if gold_pickup.count > 0 {
-            let tiles_atlas = &core_assets.tiles_atlas;
             let pos = grid_transform.to_world(grid_transform.translation + IVec2::new(0, 1));
             commands
-                .spawn_bundle(GoldBundle::new(tiles_atlas, pos))
+                .spawn_bundle(GoldBundle::new(&spawnables.gold, pos))
                 .insert(LevelSpecificComponent);
             gold_pickup.count -= 1;
         }
@@ -373,17 +513,32 @@ pub fn guard_kill_player(mut commands: Commands, players: Query<(Entity, &Overla
     }
 }
 
-pub fn restart_level(mut app_state: ResMut<State<AppStates>>, dead_players: Query<&Runner, With<Killed>>) {
-    if !dead_players.is_empty() {
-        app_state.set(AppStates::ChangeLevel).expect("failed to change state");
+/// Keeps track of how long the level has been played, and kills the runner once a level with a time limit
+/// runs out of time
+pub fn run_time_limit(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut state: ResMut<LevelState>,
+    runners: Query<Entity, (With<Runner>, Without<Killed>)>,
+) {
+    state.elapsed += time.delta_seconds();
+    if let Some(time_left) = &mut state.time_left {
+        *time_left -= time.delta_seconds();
+        if *time_left <= 0.0 {
+            for runner in runners.iter() {
+                commands.entity(runner).insert(Killed);
+            }
+        }
     }
 }
 
-pub fn pending_despawns(mut commands: Commands, time: Res<Time>, mut query: Query<(Entity, &mut DespawnAfter)>) {
-    for (entity, mut despawn_after) in query.iter_mut() {
-        despawn_after.time_remaining -= time.delta_seconds();
-        if despawn_after.time_remaining <= 0.0 {
-            commands.entity(entity).despawn_recursive();
+pub fn restart_level(mut app_state: ResMut<State<AppStates>>, mut lives: ResMut<Lives>, dead_players: Query<&Runner, With<Killed>>) {
+    if !dead_players.is_empty() {
+        lives.left = lives.left.saturating_sub(1);
+        if lives.left == 0 {
+            app_state.set(AppStates::GameOver).expect("failed to change state");
+        } else {
+            app_state.set(AppStates::ChangeLevel).expect("failed to change state");
         }
     }
 }
@@ -394,5 +549,6 @@ pub fn exit_gameplay(mut commands: Commands, to_despawn: Query<Entity, With<Leve
     }
     commands.remove_resource::<LevelResource>();
     commands.remove_resource::<LevelState>();
+    commands.remove_resource::<LevelModifiers>();
     commands.remove_resource::<SpawnableResources>();
 }
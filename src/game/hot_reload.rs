@@ -0,0 +1,78 @@
+use crate::assets::{playlist_asset::levels_in, AnimAsset, LevelDataAsset, PlaylistAsset, ScoringAsset};
+use crate::{AppStates, CoreAssets};
+use bevy::prelude::*;
+
+use super::{components::SpriteAnimator, resources::PlaylistState};
+
+/// Rebuilds the level when the file for the level being played changes on disk
+pub fn reload_level(
+    mut events: EventReader<AssetEvent<LevelDataAsset>>,
+    mut app_state: ResMut<State<AppStates>>,
+    playlist_state: Res<PlaylistState>,
+    level_datas: Res<Assets<LevelDataAsset>>,
+) {
+    let current_level = level_datas.get_handle(playlist_state.current_level());
+
+    let mut modified = false;
+    for event in events.iter() {
+        if let AssetEvent::Modified { handle } = event {
+            modified |= *handle == current_level;
+        }
+    }
+
+    if modified {
+        // going through ChangeLevel despawns everything level specific, then init_gameplay
+        // spawns the new version and rebuilds the navmesh. the level could already be changing
+        // this frame, in which case it'll pick up the new version anyways
+        app_state.set(AppStates::ChangeLevel).ok();
+    }
+}
+
+/// Swaps in the point table when it's changed on disk, points already scored stay as they are
+pub fn reload_scoring(
+    mut events: EventReader<AssetEvent<ScoringAsset>>,
+    core_assets: Res<CoreAssets>,
+    scorings: Res<Assets<ScoringAsset>>,
+    mut scoring: ResMut<ScoringAsset>,
+) {
+    for event in events.iter() {
+        if let AssetEvent::Modified { handle } = event {
+            if let Some(modified) = scorings.get(handle).filter(|_| *handle == core_assets.scoring) {
+                *scoring = modified.clone();
+            }
+        }
+    }
+}
+
+/// Restarts any running animations using an anim that was changed on disk
+pub fn reload_animations(mut events: EventReader<AssetEvent<AnimAsset>>, mut animators: Query<(&mut SpriteAnimator, &Handle<AnimAsset>)>) {
+    for event in events.iter() {
+        if let AssetEvent::Modified { handle } = event {
+            for (mut animator, _) in animators.iter_mut().filter(|(_, anim)| *anim == handle) {
+                animator.restart();
+            }
+        }
+    }
+}
+
+/// Picks up edits to the playlist being played, the current level keeps running
+pub fn reload_playlist(
+    mut events: EventReader<AssetEvent<PlaylistAsset>>,
+    asset_server: Res<AssetServer>,
+    mut playlist_state: ResMut<PlaylistState>,
+    playlists: Res<Assets<PlaylistAsset>>,
+) {
+    let mut modified = false;
+    for event in events.iter() {
+        if let AssetEvent::Modified { handle } = event {
+            modified |= handle == playlist_state.playlist_handle();
+        }
+    }
+    if !modified {
+        return;
+    }
+
+    if let Err(err) = playlist_state.reload(&playlists, |from| levels_in(from, &asset_server)) {
+        println!("WARNING:  keeping the playlist as it was: {}", err);
+    }
+}
@@ -7,14 +7,19 @@ mod resources;
 
 mod animations;
 mod gameplay;
+mod hot_reload;
 mod movement;
 
+use crate::assets::{tileset_asset::tileset_dependencies_progress, AssetLoading::track};
 use crate::BevyState;
 use ai::run_ai_guards;
+pub use ai::NavMesh;
+pub use animations::ANIMATION_EVENTS;
 use animations::*;
 use gameplay::*;
+use hot_reload::{reload_animations, reload_level, reload_playlist, reload_scoring};
 use movement::{apply_falling, apply_falling_guard, apply_movement, build_overlaps};
-pub use resources::PlaylistState;
+pub use resources::{EffectiveTileType, LevelAssets, LevelResource, Lives, PlaylistState, Score};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, SystemLabel)]
 pub enum GameplaySystem {
@@ -22,16 +27,27 @@ pub enum GameplaySystem {
     Movement,
     Overlaps,
     Animation,
+    LevelTransition,
 }
 
 pub struct GameplayPlugin<S: BevyState> {
     pub for_state: S,
+    /// The state that loads each level before it's played
+    pub loading_state: S,
 }
 
 impl<S: BevyState> Plugin for GameplayPlugin<S> {
     fn build(&self, app: &mut App) {
         use GameplaySystem::*;
 
+        app.add_event::<AnimationEvent>();
+        app.init_resource::<LevelAssets>();
+        app.add_system_set(SystemSet::on_enter(self.loading_state.clone()).with_system(load_level));
+        app.add_system_set(
+            SystemSet::on_update(self.loading_state.clone())
+                .with_system(track(level_tileset_progress))
+                .with_system(track(tileset_dependencies_progress)),
+        );
         app.add_system_set(SystemSet::on_enter(self.for_state.clone()).with_system(init_gameplay));
         app.add_system_set(
             SystemSet::on_update(self.for_state.clone())
@@ -46,17 +62,35 @@ impl<S: BevyState> Plugin for GameplayPlugin<S> {
                 .with_system(build_overlaps.label(Overlaps).after(Movement))
                 .with_system(gold_pickups.after(Overlaps))
                 .with_system(guard_kill_player.after(Overlaps))
+                .with_system(run_time_limit.after(Movement))
                 .with_system(guard_drop_treasure.after(Movement))
                 .with_system(start_guard_respawn.after(Movement).before(Overlaps))
                 .with_system(respawn_guard.after(Movement).before(Overlaps))
-                .with_system(animgraph_runner.before(Animation).after(Movement))
-                .with_system(animgraph_guard.before(Animation).after(Movement))
-                .with_system(animgraph_brick.before(Animation).after(Movement))
+                .with_system(run_animgraphs.before(Animation).after(Movement))
                 .with_system(animate_sprites.label(Animation).after(Movement))
-                .with_system(pending_despawns.after(Input).after(Movement).after(Animation))
+                .with_system(finish_burns.after(Animation))
+                .with_system(despawn_on_animation_event.after(Animation))
                 .with_system(show_exit_ladders.after(Input).after(Movement).after(Overlaps).after(Animation))
-                .with_system(next_level.after(Input).after(Movement).after(Overlaps).after(Animation))
-                .with_system(restart_level.after(Input).after(Movement).after(Overlaps).after(Animation)),
+                .with_system(
+                    next_level
+                        .label(LevelTransition)
+                        .after(Input)
+                        .after(Movement)
+                        .after(Overlaps)
+                        .after(Animation),
+                )
+                .with_system(
+                    restart_level
+                        .label(LevelTransition)
+                        .after(Input)
+                        .after(Movement)
+                        .after(Overlaps)
+                        .after(Animation),
+                )
+                .with_system(reload_level.after(LevelTransition))
+                .with_system(reload_animations.before(Animation))
+                .with_system(reload_playlist.after(LevelTransition))
+                .with_system(reload_scoring.before(Input)),
         );
         app.add_system_set(SystemSet::on_exit(self.for_state.clone()).with_system(exit_gameplay));
     }
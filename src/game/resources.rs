@@ -1,10 +1,15 @@
-use crate::{MAP_SIZE_HEIGHT, MAP_SIZE_WIDTH};
-use bevy::{math::IVec2, prelude::*, utils::HashMap};
-use rand::Rng;
+use crate::{TILE_SIZE_HEIGHT, TILE_SIZE_WIDTH};
+use bevy::{
+    math::IVec2,
+    prelude::*,
+    utils::{HashMap, HashSet},
+};
+use rand::seq::SliceRandom;
 
 use crate::assets::{
+    playlist_asset::{next_unlocked, GameOverAction, LivesConfig, PlaylistEnd, PlaylistEntry},
     LevelAsset::{LevelDataAsset, TileType},
-    PlaylistAsset,
+    PlaylistAsset, TilesetAsset,
 };
 
 #[derive(Clone, Copy, PartialEq, Debug)]
@@ -91,8 +96,8 @@ impl LevelResource {
             overrides: HashMap::default(),
         };
 
-        for x in 0..MAP_SIZE_WIDTH {
-            new_resource.respawns.push(IVec2::new(x, MAP_SIZE_HEIGHT - 2))
+        for x in 0..new_resource.width {
+            new_resource.respawns.push(IVec2::new(x, new_resource.height - 2))
         }
 
         for tile in &level_asset.tiles {
@@ -173,11 +178,28 @@ impl LevelResource {
         }
     }
 
+    pub fn width(&self) -> i32 {
+        self.width
+    }
+
+    pub fn height(&self) -> i32 {
+        self.height
+    }
+
+    /// World position of tile (0, 0), chosen so the level is centred horizontally
+    pub fn world_offset(&self) -> Vec3 {
+        Vec3::new(
+            self.width as f32 / 2.0 * TILE_SIZE_WIDTH * -1.0 + (TILE_SIZE_WIDTH / 2.0),
+            TILE_SIZE_HEIGHT / 2.0,
+            0.0,
+        )
+    }
+
     fn to_index(&self, pos: IVec2) -> usize {
         (pos.y * self.width + pos.x) as usize
     }
 
-    fn is_in_bounds(&self, pos: IVec2) -> bool {
+    pub fn is_in_bounds(&self, pos: IVec2) -> bool {
         pos.x >= 0 && pos.x < self.width && pos.y >= 0 && pos.y < self.height
     }
 
@@ -185,10 +207,9 @@ impl LevelResource {
         self.treasures
     }
 
-    pub fn get_random_respawn(&self) -> IVec2 {
-        let mut rng = rand::thread_rng();
-        let index: usize = rng.gen_range(0..self.respawns.len());
-        self.respawns[index]
+    /// A free cell on the second row from the top, a level with a full top row has none
+    pub fn get_random_respawn(&self) -> Option<IVec2> {
+        self.respawns.choose(&mut rand::thread_rng()).copied()
     }
 }
 
@@ -196,28 +217,198 @@ impl LevelResource {
 pub struct LevelState {
     pub should_complete: bool,
     pub completed: bool,
+    /// Seconds left to finish the level in, if it has a time limit
+    pub time_left: Option<f32>,
+    /// Seconds since the level started
+    pub elapsed: f32,
+    /// From the level's header, a level without a time limit pays the time bonus for finishing under it
+    pub par_seconds: Option<u32>,
+}
+
+impl LevelState {
+    /// Seconds left on the time limit, or under par when there's no limit
+    pub fn seconds_left(&self) -> Option<f32> {
+        self.time_left
+            .or_else(|| self.par_seconds.map(|par_seconds| par_seconds as f32 - self.elapsed))
+    }
+}
+
+/// Assets of the level being played. Only these stay loaded, the previous level's get dropped when
+/// ChangeLevel loads the next one
+#[derive(Default)]
+pub struct LevelAssets {
+    pub level: Handle<LevelDataAsset>,
+    /// Picked once the level is loaded, it says which theme it uses
+    pub tileset: Option<Handle<TilesetAsset>>,
+}
+
+/// Points scored in the current run of a playlist, kept across its levels
+#[derive(Default)]
+pub struct Score {
+    pub points: u32,
+}
+
+/// Tries the runner has left, dying without any left is game over
+pub struct Lives {
+    pub left: u32,
+}
+
+impl Lives {
+    pub fn new(config: &LivesConfig) -> Self {
+        Self { left: config.start }
+    }
 }
 
 pub struct PlaylistState {
     index: usize,
     playlist_handle: Handle<PlaylistAsset>,
+    /// The playlist the run started with and its seed, which a game over can start over from
+    campaign_handle: Handle<PlaylistAsset>,
+    campaign_seed: u64,
+    /// The playlist's entries in the order they're played, resolved once from the seed
+    entries: Vec<PlaylistEntry>,
+    seed: u64,
+    /// Levels completed in this run, which unlock later entries
+    completed: HashSet<String>,
+    campaign_complete: bool,
 }
 
 impl PlaylistState {
-    pub fn new(playlist: Handle<PlaylistAsset>) -> Self {
-        Self {
+    /// Starts a run of a playlist, `seed` overrides the playlist's own and `pool` lists the levels it can pick
+    /// from a folder or pack. Fails if that leaves nothing to play
+    pub fn new(
+        playlist: Handle<PlaylistAsset>,
+        playlists: &Assets<PlaylistAsset>,
+        seed: Option<u64>,
+        pool: impl Fn(&str) -> Vec<String>,
+    ) -> Result<Self, anyhow::Error> {
+        let mut playlist_state = Self {
             index: 0,
-            playlist_handle: playlist,
-        }
+            playlist_handle: Handle::default(),
+            campaign_handle: playlist.clone(),
+            campaign_seed: 0,
+            entries: Vec::new(),
+            seed: 0,
+            completed: HashSet::default(),
+            campaign_complete: false,
+        };
+        playlist_state.start_playlist(playlist, playlists, seed, &pool)?;
+        playlist_state.campaign_seed = playlist_state.seed;
+        Ok(playlist_state)
+    }
+
+    fn start_playlist(
+        &mut self,
+        playlist_handle: Handle<PlaylistAsset>,
+        playlists: &Assets<PlaylistAsset>,
+        seed: Option<u64>,
+        pool: &impl Fn(&str) -> Vec<String>,
+    ) -> Result<(), anyhow::Error> {
+        let playlist = playlists.get(&playlist_handle).unwrap();
+        let seed = seed.or(playlist.seed).unwrap_or_else(rand::random);
+        self.entries = resolve_entries(playlist, seed, pool)?;
+        self.seed = seed;
+        self.index = next_unlocked(&self.entries, 0, &self.completed).unwrap_or(0);
+        self.playlist_handle = playlist_handle;
+        Ok(())
+    }
+
+    pub fn current_level(&self) -> &str {
+        self.current_entry().level.as_str()
     }
 
-    pub fn current_level<'a>(&self, playlists: &'a Res<Assets<PlaylistAsset>>) -> &'a str {
+    pub fn current_entry(&self) -> &PlaylistEntry {
+        &self.entries[self.index]
+    }
+
+    /// Every entry of the run, in the order they're played
+    pub fn entries(&self) -> &[PlaylistEntry] {
+        &self.entries
+    }
+
+    /// Name of the section the current level is in, if it's in one
+    pub fn current_section<'a>(&self, playlists: &'a Assets<PlaylistAsset>) -> Option<&'a str> {
+        let playlist = playlists.get(&self.playlist_handle).unwrap();
+        playlist.section_of(self.index).map(|section| section.name.as_str())
+    }
+
+    /// Seed the playlist's order was resolved from, playing the playlist again with it gives the same levels
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Completes the current level and moves on to the next unlocked one, or does whatever the playlist says to
+    /// once it runs out of levels
+    pub fn next_level(&mut self, playlists: &Assets<PlaylistAsset>, pool: impl Fn(&str) -> Vec<String>) {
+        self.completed.insert(self.current_level().to_owned());
+
+        if let Some(index) = next_unlocked(&self.entries, self.index + 1, &self.completed) {
+            self.index = index;
+            return;
+        }
+
         let playlist = playlists.get(&self.playlist_handle).unwrap();
-        playlist.levels[self.index].as_str()
+        match &playlist.end {
+            PlaylistEnd::Loop => self.index = next_unlocked(&self.entries, 0, &self.completed).unwrap_or(0),
+            PlaylistEnd::Finish => self.campaign_complete = true,
+            PlaylistEnd::Chain(path) => {
+                let chained_handle = playlists.get_handle(path.as_str());
+                if playlists.contains(&chained_handle) {
+                    if let Err(err) = self.start_playlist(chained_handle, playlists, None, &pool) {
+                        println!("WARNING:  can't play {}: {}", path, err);
+                        self.campaign_complete = true;
+                    }
+                } else {
+                    println!("WARNING:  playlist chains to {:?}, which isn't loaded", path);
+                    self.campaign_complete = true;
+                }
+            }
+        }
     }
 
-    pub fn next_level(&mut self, playlists: &Res<Assets<PlaylistAsset>>) {
+    /// The last level of a playlist that finishes has been completed
+    pub fn is_campaign_complete(&self) -> bool {
+        self.campaign_complete
+    }
+
+    pub fn playlist_handle(&self) -> &Handle<PlaylistAsset> {
+        &self.playlist_handle
+    }
+
+    /// Lives of the playlist being played, chained playlists can change them
+    pub fn lives_config<'a>(&self, playlists: &'a Assets<PlaylistAsset>) -> &'a LivesConfig {
+        &playlists.get(&self.playlist_handle).unwrap().lives
+    }
+
+    /// Out of lives, either starts the campaign over with the same seed or stays on the current level
+    pub fn game_over(&mut self, playlists: &Assets<PlaylistAsset>, pool: impl Fn(&str) -> Vec<String>) -> Lives {
+        if self.lives_config(playlists).game_over == GameOverAction::Restart {
+            match Self::new(self.campaign_handle.clone(), playlists, Some(self.campaign_seed), pool) {
+                Ok(restarted) => *self = restarted,
+                Err(err) => println!("WARNING:  can't start the campaign over, continuing instead: {}", err),
+            }
+        }
+        Lives::new(self.lives_config(playlists))
+    }
+
+    /// Picks up edits to the playlist, keeping the seed and as close to the current position as possible.
+    /// The entries are left alone if the edited playlist has nothing to play
+    pub fn reload(&mut self, playlists: &Assets<PlaylistAsset>, pool: impl Fn(&str) -> Vec<String>) -> Result<(), anyhow::Error> {
         let playlist = playlists.get(&self.playlist_handle).unwrap();
-        self.index = (self.index + 1) % playlist.levels.len();
+        self.entries = resolve_entries(playlist, self.seed, &pool)?;
+        self.index = self.index.min(self.entries.len() - 1);
+        Ok(())
+    }
+}
+
+fn resolve_entries(playlist: &PlaylistAsset, seed: u64, pool: &impl Fn(&str) -> Vec<String>) -> Result<Vec<PlaylistEntry>, anyhow::Error> {
+    let pool = playlist.pick.as_ref().map(|pick| pick.pool(pool)).unwrap_or_default();
+    let entries = playlist.resolve(seed, &pool);
+    if entries.is_empty() {
+        match &playlist.pick {
+            Some(pick) => anyhow::bail!("playlist has no levels to play, and there are none to pick from {}", pick.from),
+            None => anyhow::bail!("playlist has no levels to play"),
+        }
     }
+    Ok(entries)
 }
@@ -0,0 +1,44 @@
+pub mod assets;
+pub mod camera;
+pub mod game;
+
+use assets::{AnimAsset, AtlasAsset, PlaylistAsset, ScoringAsset, TilesetAsset};
+use bevy::prelude::*;
+use std::fmt::Debug;
+use std::hash::Hash;
+
+pub const TILE_SIZE_WIDTH: f32 = 20.0;
+pub const TILE_SIZE_HEIGHT: f32 = 22.0;
+
+pub trait BevyState: Component + Debug + Clone + Eq + Hash {}
+impl<T: Component + Debug + Clone + Eq + Hash> BevyState for T {}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Component)]
+pub enum AppStates {
+    InitialLoading,
+    Testing,
+    ChangeLevel,
+    CampaignComplete,
+    /// The runner is out of lives
+    GameOver,
+    /// Some of the assets failed to load, lists them instead of playing
+    LoadingFailed,
+}
+
+#[derive(Clone, Default)]
+pub struct CoreAssets {
+    pub hole_atlas: Handle<AtlasAsset>,
+    pub debug_atlas: Handle<AtlasAsset>,
+    /// Bitmap font, it only has digits, capitals and a little punctuation
+    pub text_atlas: Handle<AtlasAsset>,
+
+    /// The startup playlist, levels are only loaded once they're played
+    pub playlist: Handle<PlaylistAsset>,
+    /// Every playlist the startup one chains to, in the order they're played
+    pub chained_playlists: Vec<Handle<PlaylistAsset>>,
+    /// Played when digging, the other anims are loaded by the tilesets using them
+    pub fire_anim: Handle<AnimAsset>,
+    /// Levels load their own tileset, this one is the fallback for themes that don't exist
+    pub default_tileset: Handle<TilesetAsset>,
+    pub scoring: Handle<ScoringAsset>,
+}
@@ -1,36 +1,22 @@
-mod assets;
-mod camera;
-mod game;
-
-use assets::PlaylistAsset;
-use assets::{
-    AnimAsset, AnimAssetPlugin, AssetLoading::ProgressCounter, AssetsLoading, LevelDataAsset, LevelDataAssetPlugin, LoadingPlugin,
-    PlaylistAssetPlugin,
+use bevy::{
+    asset::{AssetPlugin, LoadState},
+    prelude::*,
 };
-use bevy::asset::Handle;
-use bevy::{asset::AssetServerSettings, prelude::*};
-use camera::*;
-use game::PlaylistState;
-use std::fmt::Debug;
-use std::hash::Hash;
-
-pub const TILE_SIZE_WIDTH: f32 = 20.0;
-pub const TILE_SIZE_HEIGHT: f32 = 22.0;
-pub const TILE_PADDING_WIDTH: f32 = 3.0;
-pub const TILE_PADDING_HEIGHT: f32 = 3.0;
-pub const MAP_SIZE_WIDTH: i32 = 28;
-pub const MAP_SIZE_HALF_WIDTH: i32 = MAP_SIZE_WIDTH / 2;
-pub const MAP_SIZE_HEIGHT: i32 = 16;
-
-pub trait BevyState: Component + Debug + Clone + Eq + Hash {}
-impl<T: Component + Debug + Clone + Eq + Hash> BevyState for T {}
-
-#[derive(Debug, Clone, PartialEq, Eq, Hash, Component)]
-pub enum AppStates {
-    InitialLoading,
-    Testing,
-    ChangeLevel,
-}
+use loderunner::assets::{
+    animations::animgraphs_progress,
+    atlas_asset::atlas_images_progress,
+    overlay_asset_io::OverlayAssetIoPlugin,
+    playlist_asset::{levels_in, GameOverAction, PlaylistEnd, PlaylistOrder},
+    tileset_asset::{theme_path, tileset_dependencies_progress, DEFAULT_THEME},
+    AnimAssetPlugin, AnimGraphAssetPlugin,
+    AssetLoading::{track, Progress, ProgressCounter},
+    AssetsLoading, AtlasAsset, AtlasAssetPlugin, LevelDataAssetPlugin, LoadingLabel, LoadingPlugin, PlaylistAsset, PlaylistAssetPlugin,
+    ScoringAsset, ScoringAssetPlugin, TilesetAssetPlugin,
+};
+use loderunner::camera::*;
+use loderunner::game::{self, Lives, PlaylistState, Score};
+use loderunner::{AppStates, CoreAssets, TILE_SIZE_HEIGHT, TILE_SIZE_WIDTH};
+use std::path::PathBuf;
 
 #[derive(Debug, Hash, PartialEq, Eq, Clone, SystemLabel)]
 pub enum StartupSystems {
@@ -39,24 +25,15 @@ pub enum StartupSystems {
     SetupTest,
 }
 
-#[derive(Clone, Default)]
-pub struct CoreAssets {
-    pub tiles_atlas: Handle<TextureAtlas>,
-    pub guard_atlas: Handle<TextureAtlas>,
-    pub runner_atlas: Handle<TextureAtlas>,
-    pub hole_atlas: Handle<TextureAtlas>,
-    pub debug_atlas: Handle<TextureAtlas>,
-
-    pub map_handles: Vec<Handle<LevelDataAsset>>,
-    pub playlist_handles: Vec<Handle<PlaylistAsset>>,
-    pub anim_handles: Vec<Handle<AnimAsset>>,
-}
-
 #[derive(Clone)]
 pub struct StartupSettings {
     window_mode: bevy::window::WindowMode,
     playlist: String,
-    asset_directory: String,
+    /// Replays a shuffled or picked playlist in an order someone else got
+    seed: Option<u64>,
+    /// Shipped assets first, then mods. Later ones shadow files in the earlier ones
+    asset_directories: Vec<PathBuf>,
+    watch_for_changes: bool,
 }
 
 impl Default for StartupSettings {
@@ -64,7 +41,9 @@ impl Default for StartupSettings {
         Self {
             window_mode: bevy::window::WindowMode::BorderlessFullscreen,
             playlist: "playlists/classic.playlist".to_owned(),
-            asset_directory: Default::default(),
+            seed: None,
+            asset_directories: Vec::new(),
+            watch_for_changes: cfg!(debug_assertions),
         }
     }
 }
@@ -85,26 +64,41 @@ fn main() {
         ..Default::default()
     };
 
-    let startup_settings = get_startup_settings();
+    let mut startup_settings = get_startup_settings();
     window_descriptor.mode = startup_settings.window_mode;
 
+    let asset_io_plugin = OverlayAssetIoPlugin {
+        roots: startup_settings.asset_directories.clone(),
+    };
+    if startup_settings.watch_for_changes && !asset_io_plugin.can_watch_for_changes() {
+        println!("WARNING:  hot reloading is off, it doesn't work with mods or embedded assets");
+        startup_settings.watch_for_changes = false;
+    }
+
     let mut app_builder = App::new();
     app_builder
         .insert_resource(startup_settings.clone())
         .insert_resource(ClearColor(Color::rgb(0.0, 0.0, 0.0)))
         .insert_resource(window_descriptor)
-        .insert_resource(AssetServerSettings {
-            asset_folder: startup_settings.asset_directory,
-        })
         .insert_resource(CoreAssets { ..Default::default() })
-        .add_plugins(DefaultPlugins)
+        .add_plugins_with(DefaultPlugins, |group| group.add_before::<AssetPlugin, _>(asset_io_plugin))
         .add_plugin(LevelDataAssetPlugin)
         .add_plugin(AnimAssetPlugin)
+        .add_plugin(AnimGraphAssetPlugin)
+        .add_plugin(AtlasAssetPlugin)
+        .add_plugin(TilesetAssetPlugin)
         .add_plugin(PlaylistAssetPlugin)
+        .add_plugin(ScoringAssetPlugin)
         .add_plugin(ScalableOrthographicCameraPlugin)
         .add_plugin(LoadingPlugin {
             loading_state: InitialLoading,
+            next_state: ChangeLevel,
+            error_state: LoadingFailed,
+        })
+        .add_plugin(LoadingPlugin {
+            loading_state: ChangeLevel,
             next_state: Testing,
+            error_state: LoadingFailed,
         })
         .add_state(InitialLoading)
         .add_startup_system(boot.label(Boot))
@@ -113,14 +107,34 @@ fn main() {
                 .with_system(load_core_assets)
                 .with_system(core_asset_loading_onenter),
         )
-        .add_system_set(SystemSet::on_update(InitialLoading).with_system(core_asset_loading))
+        .add_system_set(
+            SystemSet::on_update(InitialLoading)
+                .with_system(core_asset_loading)
+                .with_system(load_chained_playlists.before(LoadingLabel::Pre))
+                .with_system(track(atlas_images_progress))
+                .with_system(track(tileset_dependencies_progress))
+                .with_system(track(animgraphs_progress))
+                .with_system(track(core_asset_loading_setup_playlist)),
+        )
         .add_system_set(
             SystemSet::on_exit(InitialLoading)
                 .with_system(core_asset_loading_onexit)
-                .with_system(core_asset_loading_setup_playlist),
+                .with_system(core_asset_loading_setup_scoring),
         )
-        .add_plugin(game::GameplayPlugin { for_state: Testing })
-        .add_system_set(SystemSet::on_enter(ChangeLevel).with_system(change_level));
+        .add_plugin(game::GameplayPlugin {
+            for_state: Testing,
+            loading_state: ChangeLevel,
+        })
+        .add_system_set(SystemSet::on_enter(ChangeLevel).with_system(core_asset_loading_onenter))
+        .add_system_set(SystemSet::on_update(ChangeLevel).with_system(core_asset_loading))
+        .add_system_set(SystemSet::on_exit(ChangeLevel).with_system(core_asset_loading_onexit))
+        .add_system_set(SystemSet::on_enter(CampaignComplete).with_system(campaign_complete))
+        .add_system_set(SystemSet::on_update(CampaignComplete).with_system(restart_campaign))
+        .add_system_set(SystemSet::on_exit(CampaignComplete).with_system(despawn_screen_text))
+        .add_system_set(SystemSet::on_enter(GameOver).with_system(game_over))
+        .add_system_set(SystemSet::on_update(GameOver).with_system(continue_after_game_over))
+        .add_system_set(SystemSet::on_exit(GameOver).with_system(despawn_screen_text))
+        .add_system_set(SystemSet::on_enter(LoadingFailed).with_system(loading_failed));
 
     app_builder.run();
 }
@@ -129,10 +143,23 @@ fn get_startup_settings() -> StartupSettings {
     let mut startup_settings = StartupSettings { ..Default::default() };
     let args: Vec<String> = std::env::args().collect();
 
-    // asset dir
-    let mut assets_dir = std::env::current_dir().expect("failed to get cwd");
-    assets_dir.push("assets");
-    startup_settings.asset_directory = assets_dir.into_os_string().into_string().unwrap();
+    // asset dirs, every folder in mods and then each -mod <dir> goes on top of the shipped assets
+    let cwd = std::env::current_dir().expect("failed to get cwd");
+    startup_settings.asset_directories.push(cwd.join("assets"));
+    if let Ok(mods) = std::fs::read_dir(cwd.join("mods")) {
+        let mut mod_dirs: Vec<PathBuf> = mods
+            .filter_map(|entry| Some(entry.ok()?.path()))
+            .filter(|path| path.is_dir())
+            .collect();
+        mod_dirs.sort();
+        startup_settings.asset_directories.extend(mod_dirs);
+    }
+    for (index, arg) in args.iter().enumerate() {
+        if arg == "-mod" {
+            let mod_dir = args.get(index + 1).expect("-mod needs a folder");
+            startup_settings.asset_directories.push(cwd.join(mod_dir));
+        }
+    }
 
     // window modes
     if args.contains(&String::from("-window")) {
@@ -146,79 +173,59 @@ fn get_startup_settings() -> StartupSettings {
     if args.contains(&String::from("-debug")) {
         startup_settings.playlist = "playlists/debug.playlist".to_owned();
     }
+    if let Some(seed) = args.iter().position(|arg| arg == "-seed").and_then(|index| args.get(index + 1)) {
+        startup_settings.seed = Some(seed.parse().expect("-seed needs a number"));
+    }
+
+    // hot reloading, on by default in debug builds
+    if args.contains(&String::from("-watch")) {
+        startup_settings.watch_for_changes = true;
+    }
+    if args.contains(&String::from("-nowatch")) {
+        startup_settings.watch_for_changes = false;
+    }
 
     startup_settings
 }
 
-fn boot(mut commands: Commands) {
+fn boot(mut commands: Commands, startup_settings: Res<StartupSettings>, asset_server: Res<AssetServer>) {
     commands.spawn_bundle(ScalableOrthographicCameraBundle::new(640.0, 360.0));
+
+    if startup_settings.watch_for_changes {
+        asset_server.watch_for_changes().expect("failed to watch assets for changes");
+    }
 }
 
 fn load_core_assets(
+    startup_settings: Res<StartupSettings>,
     asset_server: Res<AssetServer>,
     mut core_assets: ResMut<CoreAssets>,
-    mut texture_atlases: ResMut<Assets<TextureAtlas>>,
     mut loading: ResMut<AssetsLoading>,
 ) {
-    // tiles
-    let texture_handle = asset_server.load("tiles.png");
-    loading.add(&texture_handle);
-
-    let texture_atlas = TextureAtlas::from_grid_with_padding(
-        texture_handle,
-        Vec2::new(TILE_SIZE_WIDTH, TILE_SIZE_HEIGHT),
-        3,
-        3,
-        Vec2::new(TILE_PADDING_WIDTH, TILE_PADDING_HEIGHT),
-    );
-    let texture_atlas_handle = texture_atlases.add(texture_atlas);
-    core_assets.tiles_atlas = texture_atlas_handle;
-
-    // guard
-    let guard_texture_handle = asset_server.load("guard.png");
-    loading.add(&guard_texture_handle);
-    let guard_texture_atlas = TextureAtlas::from_grid(guard_texture_handle, Vec2::new(20.0, 22.0), 11, 2);
-    let guard_texture_atlas_handle = texture_atlases.add(guard_texture_atlas);
-    core_assets.guard_atlas = guard_texture_atlas_handle;
-
-    // runner
-    let runner_texture_handle = asset_server.load("runner.png");
-    loading.add(&runner_texture_handle);
-    let runner_texture_atlas = TextureAtlas::from_grid(runner_texture_handle, Vec2::new(20.0, 22.0), 9, 2);
-    let runner_texture_atlas_handle = texture_atlases.add(runner_texture_atlas);
-    core_assets.runner_atlas = runner_texture_atlas_handle;
-
-    // hole
-    let hole_texture_handle = asset_server.load("hole.png");
-    loading.add(&hole_texture_handle);
-    let hole_texture_atlas = TextureAtlas::from_grid(hole_texture_handle, Vec2::new(20.0, 22.0), 9, 4);
-    let hole_texture_atlas_handle = texture_atlases.add(hole_texture_atlas);
-    core_assets.hole_atlas = hole_texture_atlas_handle;
-
-    // load all the maps
-    for level_data_handle in asset_server.load_folder("levels").expect("failed to load levels") {
-        loading.add(&level_data_handle);
-        core_assets.map_handles.push(level_data_handle.typed());
-    }
+    // sprite sheets, the rest are loaded by the tilesets using them
+    let mut load_atlas = |path: &str| {
+        let atlas_handle = asset_server.load(path);
+        loading.add(&atlas_handle);
+        atlas_handle
+    };
+    core_assets.hole_atlas = load_atlas("hole.atlas");
+    core_assets.debug_atlas = load_atlas("debug.atlas");
+    core_assets.text_atlas = load_atlas("text.atlas");
 
-    // load all the playlists
-    for playlist_data_handle in asset_server.load_folder("playlists").expect("failed ot load playlists") {
-        loading.add(&playlist_data_handle);
-        core_assets.playlist_handles.push(playlist_data_handle.typed());
-    }
+    // levels and their tilesets are loaded in ChangeLevel
+    core_assets.default_tileset = asset_server.load(theme_path(DEFAULT_THEME).as_str());
+    loading.add(&core_assets.default_tileset);
 
-    // load all the anims
-    for anim_data_handle in asset_server.load_folder("anims").expect("fialed to load anims") {
-        loading.add(&anim_data_handle);
-        core_assets.anim_handles.push(anim_data_handle.typed());
-    }
+    core_assets.scoring = asset_server.load("points.scoring");
+    loading.add(&core_assets.scoring);
 
-    // debug
-    let debug_texture_handle = asset_server.load("debug.png");
-    loading.add(&debug_texture_handle);
-    let debug_texture_atlas = TextureAtlas::from_grid(debug_texture_handle, Vec2::new(20.0, 22.0), 11, 2);
-    let debug_texture_atlas_handle = texture_atlases.add(debug_texture_atlas);
-    core_assets.debug_atlas = debug_texture_atlas_handle;
+    // only the playlist being played, which loads its levels
+    core_assets.playlist = asset_server.load(startup_settings.playlist.as_str());
+    loading.add(&core_assets.playlist);
+
+    // anims of the tiles, runners and guards come with their tilesets
+    core_assets.fire_anim = asset_server.load("anims/fire.anim");
+    loading.add(&core_assets.fire_anim);
 }
 
 #[derive(Component)]
@@ -263,15 +270,257 @@ fn core_asset_loading_onexit(mut commands: Commands, to_despawn: Query<Entity, W
     }
 }
 
+/// Follows the startup playlist's chain one playlist at a time, since where it goes isn't known until the
+/// previous one is there
+fn load_chained_playlists(
+    asset_server: Res<AssetServer>,
+    playlists: Res<Assets<PlaylistAsset>>,
+    mut core_assets: ResMut<CoreAssets>,
+    mut loading: ResMut<AssetsLoading>,
+) {
+    let last = core_assets.chained_playlists.last().unwrap_or(&core_assets.playlist);
+    let chained = match playlists.get(last).map(|playlist| &playlist.end) {
+        Some(PlaylistEnd::Chain(chained)) => asset_server.load(chained.as_str()),
+        _ => return,
+    };
+
+    // a chain can loop back around
+    if chained != core_assets.playlist && !core_assets.chained_playlists.contains(&chained) {
+        loading.add(&chained);
+        core_assets.chained_playlists.push(chained);
+    }
+}
+
+/// Starts the playlist once it's there, a playlist that turns out to have nothing to play fails loading like a
+/// broken asset would
 fn core_asset_loading_setup_playlist(
+    mut started: Local<bool>,
     mut commands: Commands,
     startup_settings: Res<StartupSettings>,
+    core_assets: Res<CoreAssets>,
+    asset_server: Res<AssetServer>,
     playlists: Res<Assets<PlaylistAsset>>,
-) {
+    mut loading: ResMut<AssetsLoading>,
+) -> Progress {
+    let waiting = Progress { done: 0, total: 1 };
+    let done = Progress { done: 1, total: 1 };
+    if *started {
+        return done;
+    }
+
+    // a playlist that failed to load is listed by LoadingFailed already
+    if !playlists.contains(&core_assets.playlist) {
+        if asset_server.get_load_state(&core_assets.playlist) == LoadState::Failed {
+            return done;
+        }
+        return waiting;
+    }
+    *started = true;
+
+    match start_playlist(&startup_settings, &asset_server, &playlists) {
+        Ok(playlist_state) => {
+            commands.insert_resource(Lives::new(playlist_state.lives_config(&playlists)));
+            commands.insert_resource(Score::default());
+            commands.insert_resource(playlist_state);
+        }
+        Err(err) => loading.fail(
+            &core_assets.playlist,
+            Some(PathBuf::from(&startup_settings.playlist)),
+            err.to_string(),
+        ),
+    }
+    done
+}
+
+/// Gameplay reads the point table as a resource, so it doesn't have to be looked up every frame
+fn core_asset_loading_setup_scoring(mut commands: Commands, core_assets: Res<CoreAssets>, scorings: Res<Assets<ScoringAsset>>) {
+    // a point table that failed to load is listed by LoadingFailed
+    if let Some(scoring) = scorings.get(&core_assets.scoring) {
+        commands.insert_resource(scoring.clone());
+    }
+}
+
+fn start_playlist(
+    startup_settings: &StartupSettings,
+    asset_server: &AssetServer,
+    playlists: &Assets<PlaylistAsset>,
+) -> Result<PlaylistState, anyhow::Error> {
     let playlist_handle = playlists.get_handle(startup_settings.playlist.as_str());
-    commands.insert_resource(PlaylistState::new(playlist_handle));
+    let playlist_state = PlaylistState::new(playlist_handle.clone(), playlists, startup_settings.seed, |from| {
+        levels_in(from, asset_server)
+    })?;
+
+    // random playlists can be shared by their seed
+    let playlist = playlists.get(playlist_handle).unwrap();
+    if playlist.order == PlaylistOrder::Shuffled || playlist.pick.is_some() {
+        println!("playing {} with -seed {}", startup_settings.playlist, playlist_state.seed());
+    }
+    Ok(playlist_state)
+}
+
+fn campaign_complete(
+    mut commands: Commands,
+    playlist_state: Res<PlaylistState>,
+    playlists: Res<Assets<PlaylistAsset>>,
+    score: Res<Score>,
+    core_assets: Res<CoreAssets>,
+    atlases: Res<Assets<AtlasAsset>>,
+) {
+    let playlist = playlists.get(playlist_state.playlist_handle()).unwrap();
+    let title = playlist.title.as_deref().unwrap_or("campaign");
+    println!("{} complete with {} points, press enter to play again", title, score.points);
+
+    let lines = [
+        format!("{} complete", title),
+        String::new(),
+        format!("{} points", score.points),
+        String::new(),
+        "press enter to play again".to_owned(),
+    ];
+    spawn_text(&mut commands, atlases.get(&core_assets.text_atlas).unwrap(), &lines);
+}
+
+#[allow(clippy::too_many_arguments)]
+fn restart_campaign(
+    keyboard_input: Res<Input<KeyCode>>,
+    startup_settings: Res<StartupSettings>,
+    asset_server: Res<AssetServer>,
+    playlists: Res<Assets<PlaylistAsset>>,
+    mut playlist_state: ResMut<PlaylistState>,
+    mut lives: ResMut<Lives>,
+    mut score: ResMut<Score>,
+    mut state: ResMut<State<AppStates>>,
+) {
+    if keyboard_input.just_pressed(KeyCode::Return) {
+        *playlist_state = match start_playlist(&startup_settings, &asset_server, &playlists) {
+            Ok(playlist_state) => playlist_state,
+            Err(err) => {
+                println!("WARNING:  can't play {} again: {}", startup_settings.playlist, err);
+                return;
+            }
+        };
+        *lives = Lives::new(playlist_state.lives_config(&playlists));
+        *score = Score::default();
+        state.set(AppStates::ChangeLevel).expect("failed to restart the campaign");
+    }
+}
+
+fn game_over(
+    mut commands: Commands,
+    playlist_state: Res<PlaylistState>,
+    playlists: Res<Assets<PlaylistAsset>>,
+    score: Res<Score>,
+    core_assets: Res<CoreAssets>,
+    atlases: Res<Assets<AtlasAsset>>,
+) {
+    let action = match playlist_state.lives_config(&playlists).game_over {
+        GameOverAction::Restart => "start over",
+        GameOverAction::Continue => "continue",
+    };
+    println!("game over with {} points, press enter to {}", score.points, action);
+
+    let lines = [
+        "game over".to_owned(),
+        String::new(),
+        format!("{} points", score.points),
+        String::new(),
+        format!("press enter to {}", action),
+    ];
+    spawn_text(&mut commands, atlases.get(&core_assets.text_atlas).unwrap(), &lines);
 }
 
-fn change_level(mut state: ResMut<State<AppStates>>) {
-    state.set(AppStates::Testing).expect("failed to return to game state");
+#[allow(clippy::too_many_arguments)]
+fn continue_after_game_over(
+    keyboard_input: Res<Input<KeyCode>>,
+    asset_server: Res<AssetServer>,
+    playlists: Res<Assets<PlaylistAsset>>,
+    mut playlist_state: ResMut<PlaylistState>,
+    mut lives: ResMut<Lives>,
+    mut score: ResMut<Score>,
+    mut state: ResMut<State<AppStates>>,
+) {
+    if keyboard_input.just_pressed(KeyCode::Return) {
+        // starting over is a new run, continuing keeps the score
+        if playlist_state.lives_config(&playlists).game_over == GameOverAction::Restart {
+            *score = Score::default();
+        }
+        *lives = playlist_state.game_over(&playlists, |from| levels_in(from, &asset_server));
+        state.set(AppStates::ChangeLevel).expect("failed to continue after game over");
+    }
+}
+
+fn loading_failed(
+    mut commands: Commands,
+    mut clear_color: ResMut<ClearColor>,
+    loading: Res<AssetsLoading>,
+    core_assets: Res<CoreAssets>,
+    atlases: Res<Assets<AtlasAsset>>,
+) {
+    clear_color.0 = Color::rgb(0.3, 0.0, 0.0);
+
+    let mut lines = vec![format!("{} assets failed to load", loading.failed().len())];
+    println!("ERROR: {}", lines[0]);
+    for failed in loading.failed() {
+        let path = failed
+            .path
+            .as_ref()
+            .map_or_else(|| format!("{:?}", failed.handle), |path| path.display().to_string());
+        println!("  {}: {}", path, failed.error);
+
+        lines.push(String::new());
+        lines.push(format!("{}:", path));
+        lines.extend(failed.error.lines().map(|line| format!("  {}", line)));
+    }
+
+    // the font could be what failed, the errors are printed either way
+    if let Some(text_atlas) = atlases.get(&core_assets.text_atlas) {
+        spawn_text(&mut commands, text_atlas, &lines);
+    }
+}
+
+/// A glyph drawn by `spawn_text`
+#[derive(Component)]
+struct ScreenText;
+
+/// Draws lines of text from the top left of the screen at half size, wrapping anything too long to fit
+fn spawn_text(commands: &mut Commands, text_atlas: &AtlasAsset, lines: &[String]) {
+    let scale = 0.5;
+    let glyph_size = Vec2::new(TILE_SIZE_WIDTH, TILE_SIZE_HEIGHT) * scale;
+    let columns = ((640.0 - TILE_SIZE_WIDTH * 2.0) / glyph_size.x) as usize;
+    let top_left = Vec3::new(-320.0 + TILE_SIZE_WIDTH, 180.0 - TILE_SIZE_HEIGHT, 0.0);
+
+    let wrapped = lines.iter().flat_map(|line| {
+        let chars: Vec<char> = line.chars().collect();
+        let rows: Vec<String> = chars.chunks(columns).map(|row| row.iter().collect()).collect();
+        // empty lines still take up a row
+        if rows.is_empty() {
+            vec![String::new()]
+        } else {
+            rows
+        }
+    });
+    for (row, line) in wrapped.enumerate() {
+        for (column, glyph) in line.chars().enumerate() {
+            // there's no lower case, anything else the font doesn't have is left blank
+            let sprite = match text_atlas.sprite(&glyph.to_ascii_uppercase().to_string()) {
+                Some(sprite) => sprite,
+                None => continue,
+            };
+            let offset = Vec3::new(column as f32 * glyph_size.x, row as f32 * -glyph_size.y, 0.0);
+            commands
+                .spawn_bundle(SpriteSheetBundle {
+                    sprite,
+                    texture_atlas: text_atlas.texture_atlas.clone(),
+                    transform: Transform::from_scale(Vec3::splat(scale)).with_translation(top_left + offset),
+                    ..Default::default()
+                })
+                .insert(ScreenText);
+        }
+    }
+}
+
+fn despawn_screen_text(mut commands: Commands, to_despawn: Query<Entity, With<ScreenText>>) {
+    for entity in to_despawn.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
 }